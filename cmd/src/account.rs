@@ -1,30 +1,38 @@
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
 use clap::Args;
-use log::info;
-use near_crypto::{InMemorySigner, KeyType, SecretKey};
-use near_jsonrpc_client::JsonRpcClient;
+use log::{info, warn};
+use near_crypto::{InMemorySigner, SecretKey, Signer};
 use near_ops::block_service::BlockService;
-use near_ops::rpc_response_handler::{ResponseCheckSeverity, RpcResponseHandler};
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::rpc_response_handler::ResponseCheckSeverity;
+use near_ops::send_loop::SendLoop;
+use near_ops::verification::query_account_states;
 use near_ops::{
-    account::{new_create_subaccount_actions, Account},
-    rpc::{new_request, view_access_key},
+    account::{
+        accounts_from_dir, new_add_full_access_key_action, new_create_subaccount_actions,
+        parse_key_type_selection, Account, AccountKey, KeyTypeSelection,
+    },
+    rpc::{
+        is_executed, is_transaction_and_receipts_success, parse_tx_execution_status,
+        view_access_key, view_account,
+    },
 };
 use near_primitives::views::TxExecutionStatus;
 use near_primitives::{
-    transaction::{Transaction, TransactionV0},
+    account::FunctionCallPermission,
+    action::{Action, DeleteAccountAction},
+    transaction::{SignedTransaction, Transaction, TransactionV0},
     types::AccountId,
 };
-use tokio::sync::mpsc;
-use tokio::time;
 
 #[derive(Args, Debug)]
 pub struct CreateSubAccountsArgs {
-    /// TODO try to have single arg for all commands
-    #[arg(long)]
-    pub rpc_url: String,
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
     #[arg(long)]
     pub signer_key_path: PathBuf,
     /// Starting nonce > current_nonce to send transactions to create sub accounts.
@@ -48,51 +56,48 @@ pub struct CreateSubAccountsArgs {
     /// Amount to deposit with each sub-account.
     #[arg(long)]
     pub deposit: u128,
-    #[arg(long)]
-    /// Acts as upper bound on the number of concurrently open RPC requests.
-    pub channel_buffer_size: usize,
-    /// After each tick (in microseconds) a transaction is sent. If the hardware cannot keep up with
-    /// that or if the NEAR node is congested, transactions are sent at a slower rate.
-    #[arg(long)]
-    pub interval_duration_micros: u64,
+    /// Number of full access keys added to each sub account. Benchmarks rotate through the keys
+    /// of an account, which allows sending many transactions from one account without nonce
+    /// contention.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub num_keys: u64,
+    /// Type of generated keys: `ed25519`, `secp256k1` or `mix:<ratio>` where `<ratio>` is the
+    /// fraction of sub accounts with `secp256k1` keys. All keys of a sub account have the same type.
+    #[arg(long, value_parser = parse_key_type_selection, default_value = "ed25519")]
+    pub key_type: KeyTypeSelection,
     /// Directory where created user account data (incl. key and nonce) is stored.
     #[arg(long)]
     pub user_data_dir: PathBuf,
+    /// Status the RPC waits for before responding to a transaction, e.g. `FINAL`. Must be at least
+    /// `EXECUTED_OPTIMISTIC` since the nonces of created accounts are queried afterwards.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
 }
 
 pub async fn create_sub_accounts(args: &CreateSubAccountsArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
     let signer = InMemorySigner::from_file(&args.signer_key_path)?;
 
-    let client = JsonRpcClient::connect(&args.rpc_url);
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
     let block_service = Arc::new(BlockService::new(client.clone()).await);
     block_service.clone().start().await;
 
-    let mut interval = time::interval(Duration::from_micros(args.interval_duration_micros));
-    let timer = Instant::now();
-
     let mut sub_accounts: Vec<Account> =
         Vec::with_capacity(args.num_sub_accounts.try_into().unwrap());
-
-    // Before a request is made, a permit to send into the channel is awaited. Hence buffer size
-    // limits the number of outstanding requests. This helps to avoid congestion.
-    // TODO find reasonable buffer size.
-    let (channel_tx, channel_rx) = mpsc::channel(args.channel_buffer_size);
-
-    let wait_until = TxExecutionStatus::ExecutedOptimistic;
-    let wait_until_channel = wait_until.clone();
-    let num_expected_responses = args.num_sub_accounts;
-    let response_handler_task = tokio::task::spawn(async move {
-        let mut rpc_response_handler = RpcResponseHandler::new(
-            channel_rx,
-            wait_until_channel,
-            ResponseCheckSeverity::Assert,
-            num_expected_responses,
-        );
-        rpc_response_handler.handle_all_responses().await;
-    });
-
-    for i in 0..args.num_sub_accounts {
-        let sub_account_key = SecretKey::from_random(KeyType::ED25519);
+    SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_response_check_severity(ResponseCheckSeverity::Assert)
+    .run(args.num_sub_accounts, |i| {
+        let key_type = args.key_type.key_type(i);
+        let sub_account_key = SecretKey::from_random(key_type);
         let sub_account_id: AccountId = {
             let subname = if let Some(prefix) = &args.sub_account_prefix {
                 format!("{prefix}_user_{i}")
@@ -101,68 +106,336 @@ pub async fn create_sub_accounts(args: &CreateSubAccountsArgs) -> anyhow::Result
             };
             format!("{subname}.{}", signer.account_id).parse()?
         };
+        let mut sub_account = Account::new(sub_account_id.clone(), sub_account_key.clone(), 0);
+        let mut actions =
+            new_create_subaccount_actions(sub_account_key.public_key().clone(), args.deposit);
+        for _ in 1..args.num_keys {
+            let key = AccountKey::new(SecretKey::from_random(key_type), 0);
+            actions.push(new_add_full_access_key_action(key.public_key.clone()));
+            sub_account.additional_keys.push(key);
+        }
         let tx = Transaction::V0(TransactionV0 {
             signer_id: signer.account_id.clone(),
             public_key: signer.public_key().clone(),
             nonce: args.nonce + i,
             receiver_id: sub_account_id.clone(),
             block_hash: block_service.get_block_hash(),
-            actions: new_create_subaccount_actions(
-                sub_account_key.public_key().clone(),
-                args.deposit,
-            ),
-        });
-        let request = new_request(tx, wait_until.clone(), signer.clone());
-
-        interval.tick().await;
-        let client = client.clone();
-        // Await permit before sending the request to make channel buffer size a limit for the
-        // number of outstanding requests.
-        let permit = channel_tx.clone().reserve_owned().await.unwrap();
-        // The spawned task starts running immediately. Assume with interval between spanning them
-        // this leads to transaction nonces hitting the node in order.
-        tokio::spawn(async move {
-            let res = client.call(request).await;
-            permit.send(res);
+            actions,
         });
+        sub_accounts.push(sub_account);
+        Ok(tx.sign(&Signer::from(signer.clone())))
+    })
+    .await?;
 
-        sub_accounts.push(Account::new(sub_account_id, sub_account_key, 0));
+    info!("Querying nonces of newly created sub accounts.");
+
+    // Nonces of new access keys are set by nearcore: https://github.com/near/nearcore/pull/4064
+    // Query them from the rpc to write `Accounts` with valid nonces to disk.
+    let states = query_account_states(&client, &sub_accounts).await?;
+    for (account, state) in sub_accounts.iter_mut().zip(states.iter()) {
+        account.set_nonces(&state.nonces);
+        account.write_to_dir(&args.user_data_dir)?;
     }
 
+    info!("RPC connections: {}", client.connection_stats());
+
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+pub struct RefillAccountsArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Account that funds the top ups. Its nonce is queried from the rpc.
+    #[arg(long)]
+    pub signer_key_path: PathBuf,
+    /// Directory with the data of accounts to refill.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    /// Accounts with a balance below this are topped up.
+    #[arg(long)]
+    pub threshold: u128,
+    /// Balance that accounts below `threshold` are topped up to.
+    #[arg(long)]
+    pub top_up_to: u128,
+    /// Status the RPC waits for before responding to a transaction.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
+}
+
+/// Sends a transfer from the signer to every account whose balance is below the threshold. A
+/// transaction has a single receiver, so each top up is a separate transaction. Transfers are sent
+/// concurrently, bounded by `channel_buffer_size`.
+pub async fn refill_accounts(args: &RefillAccountsArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        args.top_up_to >= args.threshold,
+        "--top-up-to must be at least --threshold"
+    );
+    let signer = InMemorySigner::from_file(&args.signer_key_path)?;
+    let accounts = accounts_from_dir(&args.user_data_dir)?;
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+
+    info!("Querying balances of {} accounts", accounts.len());
+    let states = query_account_states(&client, &accounts).await?;
+    let top_ups: Vec<(AccountId, u128)> = accounts
+        .iter()
+        .zip(states.iter())
+        .filter(|(_, state)| state.balance < args.threshold)
+        .map(|(account, state)| (account.id.clone(), args.top_up_to - state.balance))
+        .collect();
+    let total_amount: u128 = top_ups.iter().map(|(_, amount)| amount).sum();
     info!(
-        "Sent {} txs in {:.2} seconds",
-        args.num_sub_accounts,
-        timer.elapsed().as_secs_f64()
+        "Topping up {} accounts with {} in total",
+        top_ups.len(),
+        total_amount
     );
+    if top_ups.is_empty() {
+        return Ok(());
+    }
 
-    // Ensure all rpc responses are handled.
-    response_handler_task
-        .await
-        .expect("response handler tasks should succeed");
+    let mut nonce = view_access_key(&client, signer.account_id.clone(), signer.public_key())
+        .await?
+        .nonce;
 
-    info!("Querying nonces of newly created sub accounts.");
+    let signer_id = signer.account_id.clone();
+    let signer = Signer::from(signer);
+    SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_response_check_severity(ResponseCheckSeverity::Assert)
+    .run(u64::try_from(top_ups.len()).unwrap(), |i| {
+        let (receiver_id, amount) = &top_ups[usize::try_from(i).unwrap()];
+        nonce += 1;
+        Ok(SignedTransaction::send_money(
+            nonce,
+            signer_id.clone(),
+            receiver_id.clone(),
+            &signer,
+            *amount,
+            block_service.get_block_hash(),
+        ))
+    })
+    .await?;
 
-    // Nonces of new access keys are set by nearcore: https://github.com/near/nearcore/pull/4064
-    // Query them from the rpc to write `Accounts` with valid nonces to disk.
-    // TODO use `JoinSet`, e.g. by storing accounts in map instead of vec.
-    let mut get_access_key_tasks = Vec::with_capacity(sub_accounts.len());
-    // Use an interval to avoid overwhelming the node with requests.
-    let mut interval = time::interval(Duration::from_micros(150));
-    for account in sub_accounts.clone().into_iter() {
-        interval.tick().await;
-        let client = client.clone();
-        get_access_key_tasks.push(tokio::spawn(async move {
-            view_access_key(&client, account.id.clone(), account.public_key.clone()).await
-        }))
-    }
+    info!("RPC connections: {}", client.connection_stats());
+
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+pub struct DeleteAccountsArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// The signer is the beneficiary of deleted accounts. Its key is not used for signing.
+    #[arg(long)]
+    pub signer_key_path: PathBuf,
+    /// Directory with the data of accounts to delete. Files of deleted accounts are removed.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    /// Status the RPC waits for before responding to a transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` to know whether an account was deleted.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
+}
+
+/// Deletes all accounts in `user_data_dir`, transferring their remaining balance to the signer.
+///
+/// Each account signs its own deletion with the nonce queried from the rpc. The file of an account
+/// is removed only if its deletion succeeded, so the command can be re-run to retry failures.
+pub async fn delete_accounts(args: &DeleteAccountsArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
+    let beneficiary_id = InMemorySigner::from_file(&args.signer_key_path)?.account_id;
+    let accounts = accounts_from_dir(&args.user_data_dir)?;
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+
+    info!("Querying state of {} accounts", accounts.len());
+    let states = query_account_states(&client, &accounts).await?;
+    let beneficiary_balance_before = view_account(&client, beneficiary_id.clone()).await?.amount;
+
+    let balances: Vec<u128> = states.iter().map(|state| state.balance).collect();
+    let results_accounts = accounts.clone();
+    let user_data_dir = args.user_data_dir.clone();
+    let (_, (num_deleted, deleted_balance)) = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .run_tagged(
+        u64::try_from(accounts.len()).unwrap(),
+        |i| {
+            let idx = usize::try_from(i).unwrap();
+            let account = &accounts[idx];
+            let transaction = SignedTransaction::from_actions(
+                states[idx].nonces[0] + 1,
+                account.id.clone(),
+                account.id.clone(),
+                &account.as_signer(),
+                vec![Action::DeleteAccount(DeleteAccountAction {
+                    beneficiary_id: beneficiary_id.clone(),
+                })],
+                block_service.get_block_hash(),
+                0,
+            );
+            Ok((transaction, idx))
+        },
+        |mut responses| async move {
+            let mut num_deleted = 0;
+            let mut deleted_balance = 0;
+            while let Some((idx, response)) = responses.recv().await {
+                let account = &results_accounts[idx];
+                match response.result {
+                    Ok(response) if is_transaction_and_receipts_success(&response) => {
+                        // Keep receiving, the send loop relies on responses being consumed.
+                        if let Err(err) = account.remove_from_dir(&user_data_dir) {
+                            warn!(
+                                "deleted {} but failed to remove its file: {err}",
+                                account.id
+                            );
+                        }
+                        num_deleted += 1;
+                        deleted_balance += balances[idx];
+                    }
+                    Ok(response) => warn!(
+                        "deleting {} failed: {:?}",
+                        account.id, response.final_execution_outcome
+                    ),
+                    Err(err) => warn!("deleting {} failed: {err}", account.id),
+                }
+            }
+            (num_deleted, deleted_balance)
+        },
+    )
+    .await?;
+
+    let beneficiary_balance_after = view_account(&client, beneficiary_id.clone()).await?.amount;
+    info!("Deleted {num_deleted} of {} accounts", accounts.len());
+    let beneficiary_balance_change =
+        i128::try_from(beneficiary_balance_after)? - i128::try_from(beneficiary_balance_before)?;
+    info!(
+        "Deleted accounts held {deleted_balance}, balance of {beneficiary_id} changed by {}",
+        beneficiary_balance_change
+    );
+    info!("RPC connections: {}", client.connection_stats());
 
-    for (i, task) in get_access_key_tasks.into_iter().enumerate() {
-        let response = task.await.expect("join should succeed");
-        let nonce = response?.nonce;
-        let account = sub_accounts.get_mut(i).unwrap();
-        account.nonce = nonce;
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+pub struct AddKeysArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Directory with the data of accounts to which keys are added.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    /// Number of keys to add to each account.
+    #[arg(long)]
+    pub num_keys: u64,
+    /// If provided, function call access keys restricted to calling this contract are added.
+    /// Otherwise the added keys have full access.
+    #[arg(long)]
+    pub function_call_receiver_id: Option<AccountId>,
+    /// Comma separated methods function call access keys may call. If empty, all methods of the
+    /// receiver may be called.
+    #[arg(long, value_delimiter = ',', requires = "function_call_receiver_id")]
+    pub function_call_method_names: Vec<String>,
+    /// Amount function call access keys may spend on gas and fees. Unlimited if not provided.
+    #[arg(long, requires = "function_call_receiver_id")]
+    pub function_call_allowance: Option<u128>,
+    /// Type of added keys: `ed25519`, `secp256k1` or `mix:<ratio>` where `<ratio>` is the
+    /// fraction of `secp256k1` keys.
+    #[arg(long, value_parser = parse_key_type_selection, default_value = "ed25519")]
+    pub key_type: KeyTypeSelection,
+    /// Status the RPC waits for before responding to a transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` since the nonces of added keys are queried afterwards.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
+}
+
+/// Adds access keys to all accounts in `user_data_dir`. Each account sends one transaction with an
+/// `AddKey` action per new key, signed with its first key.
+pub async fn add_keys(args: &AddKeysArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    let function_call_permission =
+        args.function_call_receiver_id
+            .as_ref()
+            .map(|receiver_id| FunctionCallPermission {
+                allowance: args.function_call_allowance,
+                receiver_id: receiver_id.to_string(),
+                method_names: args.function_call_method_names.clone(),
+            });
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+
+    let mut num_generated_keys = 0;
+    SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_response_check_severity(ResponseCheckSeverity::Assert)
+    .run(u64::try_from(accounts.len()).unwrap(), |i| {
+        let account = &mut accounts[usize::try_from(i).unwrap()];
+        let mut actions = vec![];
+        for _ in 0..args.num_keys {
+            let secret_key = SecretKey::from_random(args.key_type.key_type(num_generated_keys));
+            num_generated_keys += 1;
+            let key = match &function_call_permission {
+                Some(permission) => {
+                    AccountKey::new_function_call(secret_key, 0, permission.clone())
+                }
+                None => AccountKey::new(secret_key, 0),
+            };
+            actions.push(key.add_key_action());
+            account.additional_keys.push(key);
+        }
+        Ok(SignedTransaction::from_actions(
+            account.get_and_bump_nonce(),
+            account.id.clone(),
+            account.id.clone(),
+            &account.as_signer(),
+            actions,
+            block_service.get_block_hash(),
+            0,
+        ))
+    })
+    .await?;
+
+    info!("Querying nonces of added keys.");
+    let states = query_account_states(&client, &accounts).await?;
+    for (account, state) in accounts.iter_mut().zip(states.iter()) {
+        account.set_nonces(&state.nonces);
         account.write_to_dir(&args.user_data_dir)?;
     }
 
+    info!("RPC connections: {}", client.connection_stats());
+
     Ok(())
 }
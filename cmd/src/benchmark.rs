@@ -1,134 +1,278 @@
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use clap::Args;
-use log::info;
-use near_jsonrpc_client::methods::send_tx::RpcSendTransactionRequest;
-use near_jsonrpc_client::JsonRpcClient;
+use clap::{Args, ValueEnum};
+use log::{info, warn};
 use near_ops::account::accounts_from_dir;
 use near_ops::block_service::BlockService;
-use near_ops::rpc_response_handler::{ResponseCheckSeverity, RpcResponseHandler};
+use near_ops::distribution::{AccountDistributionArgs, AccountPairSampler};
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::report::BenchmarkReport;
+use near_ops::rng::SeedArgs;
+use near_ops::rpc::parse_tx_execution_status;
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::{SendLoop, SendLoopReport};
+use near_ops::tx_lifecycle::{TrackedTx, TxLifecycleTracker};
+use near_ops::tx_status_tracker::TxStatusTracker;
+use near_ops::verification::{query_account_states, verify_native_transfers, VerificationArgs};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::views::TxExecutionStatus;
-use rand::distributions::{Distribution, Uniform};
 use tokio::sync::mpsc;
-use tokio::time;
 
 #[derive(Args, Debug)]
 pub struct BenchmarkNativeTransferArgs {
-    /// TODO try to have single arg for all commands
-    #[arg(long)]
-    pub rpc_url: String,
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
     #[arg(long)]
     pub user_data_dir: PathBuf,
     #[arg(long)]
     pub num_transfers: u64,
-    /// Acts as upper bound on the number of concurrently open RPC requests.
     #[arg(long)]
-    pub channel_buffer_size: usize,
-    /// After each tick (in microseconds) a transaction is sent. If the hardware cannot keep up with
-    /// that or if the NEAR node is congested, transactions are sent at a slower rate.
+    pub amount: u128,
+    #[command(flatten)]
+    pub account_distribution: AccountDistributionArgs,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+    /// RPC method used to submit transactions.
+    #[arg(long, value_enum, default_value_t = SendMode::SendTx)]
+    pub send_mode: SendMode,
+    /// With `--send-mode broadcast-tx-async`, the interval (in milliseconds) at which the status
+    /// of transactions that are not yet executed is polled.
+    #[arg(long, default_value_t = 500)]
+    pub tx_status_poll_interval_millis: u64,
+    /// With `--send-mode broadcast-tx-async`, upper bound on the number of concurrently open
+    /// status requests.
+    #[arg(long, default_value_t = 64)]
+    pub tx_status_max_concurrent_polls: usize,
+    /// With `--send-mode broadcast-tx-async`, transactions not executed within this many seconds
+    /// after submission are no longer polled and counted as timed out.
+    #[arg(long, default_value_t = 60)]
+    pub tx_status_timeout_secs: u64,
+    /// Status the RPC waits for before responding to a transaction. Unless it is `NONE`, the time
+    /// each transaction takes to reach `INCLUDED`, `EXECUTED_OPTIMISTIC` and `FINAL` (up to
+    /// `wait_until`) is recorded. Ignored with `--send-mode broadcast-tx-async`.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "NONE")]
+    pub wait_until: TxExecutionStatus,
+    /// Track when transactions are included and executed by scanning the chunks of new blocks.
+    /// Unlike `--send-mode broadcast-tx-async`, this requires no status request per transaction.
     #[arg(long)]
-    pub interval_duration_micros: u64,
+    pub track_tx_lifecycle: bool,
+    /// With `--track-tx-lifecycle`, the interval (in milliseconds) at which new blocks are polled.
+    #[arg(long, default_value_t = 250)]
+    pub block_poll_interval_millis: u64,
+    /// With `--track-tx-lifecycle`, transactions not included and executed within this many
+    /// blocks after being sent are given up on.
+    #[arg(long, default_value_t = 100)]
+    pub tx_lifecycle_timeout_blocks: u64,
+    #[command(flatten)]
+    pub verification: VerificationArgs,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
     #[arg(long)]
-    pub amount: u128,
+    pub report_path: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+pub enum SendMode {
+    /// `send_tx` with `wait_until: NONE`. Responses are checked by `RpcResponseHandler`.
+    SendTx,
+    /// `broadcast_tx_async` which returns only the transaction hash. The status of transactions
+    /// is polled in the background to measure inclusion and execution latency.
+    BroadcastTxAsync,
 }
 
 pub async fn benchmark_native_transfers(args: &BenchmarkNativeTransferArgs) -> anyhow::Result<()> {
     let mut accounts = accounts_from_dir(&args.user_data_dir)?;
     assert!(accounts.len() >= 2);
 
-    let mut interval = time::interval(Duration::from_micros(args.interval_duration_micros));
-    let timer = Instant::now();
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let states_before = if args.verification.verify {
+        info!("Querying state of accounts for verification after the run");
+        Some(query_account_states(&client, &accounts).await?)
+    } else {
+        None
+    };
 
-    let between = Uniform::from(0..accounts.len());
-    let mut rng = rand::thread_rng();
+    let mut account_pairs = AccountPairSampler::new(&args.account_distribution, accounts.len());
+    let (mut rng, seed) = args.seed.rng();
 
-    let client = JsonRpcClient::connect(&args.rpc_url);
-    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    let block_service = if args.track_tx_lifecycle {
+        let refresh_interval = Duration::from_millis(args.block_poll_interval_millis);
+        BlockService::with_refresh_interval(client.clone(), refresh_interval).await
+    } else {
+        BlockService::new(client.clone()).await
+    };
+    let block_service = Arc::new(block_service);
     block_service.clone().start().await;
+    let instrumentation = Instrumentation::start(
+        &client,
+        &args.instrumentation,
+        Some(block_service.clone()),
+        args.send.interval(),
+    )
+    .await?;
+    let start_height = block_service.get_block_height();
 
-    // Before a request is made, a permit to send into the channel is awaited. Hence buffer size
-    // limits the number of outstanding requests. This helps to avoid congestion.
-    // TODO find reasonable buffer size.
-    let (channel_tx, channel_rx) = mpsc::channel(args.channel_buffer_size);
-
-    let wait_until = TxExecutionStatus::None;
-    let wait_until_channel = wait_until.clone();
-    let num_expected_responses = args.num_transfers;
-    let response_handler_task = tokio::task::spawn(async move {
-        let mut rpc_response_handler = RpcResponseHandler::new(
-            channel_rx,
-            wait_until_channel,
-            ResponseCheckSeverity::Log,
-            num_expected_responses,
-        );
-        rpc_response_handler.handle_all_responses().await;
-    });
-
-    for i in 0..args.num_transfers {
-        let idx_sender = usize::try_from(i % u64::try_from(accounts.len()).unwrap()).unwrap();
-        let idx_receiver = {
-            let mut idx = between.sample(&mut rng);
-            if idx == idx_sender {
-                // Avoid creating a transaction where an account sends NEAR to itself.
-                // Relies on accounts.len() > 2 (asserted above).
-                if idx < accounts.len() - 1 {
-                    idx += 1;
-                } else {
-                    idx = 0
-                }
-            }
-            idx
-        };
+    let (mut lifecycle_tx, lifecycle_task) = if args.track_tx_lifecycle {
+        let (lifecycle_tx, lifecycle_rx) = mpsc::unbounded_channel();
+        let tracker = TxLifecycleTracker::new(
+            client.clone(),
+            lifecycle_rx,
+            block_service.subscribe(),
+            block_service.get_block_height(),
+            args.tx_lifecycle_timeout_blocks,
+        )
+        .await?;
+        (Some(lifecycle_tx), Some(tokio::spawn(tracker.run())))
+    } else {
+        (None, None)
+    };
+
+    // Hashes of sent transactions, to check their inclusion when verifying.
+    let mut sent_txs = vec![];
+    let next_tx = |_| {
+        let (idx_sender, idx_receiver) = account_pairs.sample(&mut rng);
 
-        let sender = &accounts[idx_sender];
-        let receiver = &accounts[idx_receiver];
+        // Rotating through the keys of the sender avoids nonce contention for accounts with
+        // multiple access keys.
+        let (signer, nonce) = accounts[idx_sender].next_signer_and_nonce();
         let transaction = SignedTransaction::send_money(
-            sender.nonce + 1,
-            sender.id.clone(),
-            receiver.id.clone(),
-            &sender.as_signer(),
+            nonce,
+            accounts[idx_sender].id.clone(),
+            accounts[idx_receiver].id.clone(),
+            &signer,
             args.amount,
             block_service.get_block_hash(),
         );
-        let request = RpcSendTransactionRequest {
-            signed_transaction: transaction,
-            wait_until: wait_until.clone(),
-        };
 
-        interval.tick().await;
-        let client = client.clone();
-        // Await permit before sending the request to make channel buffer size a limit for the
-        // number of outstanding requests.
-        let permit = channel_tx.clone().reserve_owned().await.unwrap();
-        tokio::spawn(async move {
-            let res = client.call(request).await;
-            permit.send(res);
-        });
-        if i > 0 && i % 10000 == 0 {
-            info!("num txs sent: {}", i);
+        if states_before.is_some() {
+            sent_txs.push(transaction.get_hash());
+        }
+        if let Some(tx) = &lifecycle_tx {
+            let tracked_tx = TrackedTx::new(transaction.clone(), block_service.get_block_height());
+            if tx.send(tracked_tx).is_err() {
+                warn!("Lifecycle tracker stopped, no longer tracking transactions");
+                lifecycle_tx = None;
+            }
+        }
+        Ok(transaction)
+    };
+    let send_loop = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        // TODO find reasonable buffer size.
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_finality_recording();
+    let (
+        SendLoopReport {
+            send_duration,
+            finality_latency,
+            tx_costs,
+        },
+        tx_status,
+    ) = match args.send_mode {
+        SendMode::SendTx => (send_loop.run(args.num_transfers, next_tx).await?, None),
+        SendMode::BroadcastTxAsync => {
+            let (send_duration, mut tx_status) = send_loop
+                .run_broadcast(args.num_transfers, next_tx, |receiver| {
+                    TxStatusTracker::new(
+                        client.clone(),
+                        receiver,
+                        Duration::from_millis(args.tx_status_poll_interval_millis),
+                        args.tx_status_max_concurrent_polls,
+                        Duration::from_secs(args.tx_status_timeout_secs),
+                    )
+                })
+                .await?;
+            let send_loop_report = SendLoopReport {
+                send_duration,
+                finality_latency: None,
+                tx_costs: std::mem::take(&mut tx_status.tx_costs),
+            };
+            (send_loop_report, Some(tx_status))
         }
+    };
 
-        let sender = accounts.get_mut(idx_sender).unwrap();
-        sender.nonce += 1;
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
     }
 
-    info!(
-        "Sent {} txs in {:.2} seconds",
-        args.num_transfers,
-        timer.elapsed().as_secs_f64()
-    );
+    // The lifecycle tracker stops once its channel is closed and all transactions are handled.
+    drop(lifecycle_tx);
 
-    for account in accounts.iter() {
-        account.write_to_dir(&args.user_data_dir)?;
+    let tx_lifecycle = match lifecycle_task {
+        Some(task) => match task.await {
+            Ok(tx_lifecycle) => {
+                tx_lifecycle.log_summary();
+                Some(tx_lifecycle)
+            }
+            Err(err) => {
+                warn!("Lifecycle tracker failed, skipping lifecycle stats: {err}");
+                None
+            }
+        },
+        None => None,
+    };
+
+    let verification = match &states_before {
+        Some(states_before) => {
+            let verification = verify_native_transfers(
+                &client,
+                &accounts,
+                states_before,
+                start_height,
+                &sent_txs,
+                &args.verification,
+            )
+            .await?;
+            verification.log_summary();
+            Some(verification)
+        }
+        None => None,
+    };
+    let verification_failed = verification
+        .as_ref()
+        .is_some_and(|verification| !verification.is_ok());
+
+    let instrumentation = instrumentation.finish(&tx_costs).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(args.num_transfers),
+            send_duration,
+            // `wait_until` is ignored when broadcasting.
+            wait_until: match args.send_mode {
+                SendMode::SendTx => Some(args.wait_until.clone()),
+                SendMode::BroadcastTxAsync => None,
+            },
+            seed: Some(seed),
+            connection_stats,
+            sender_distribution: Some(args.account_distribution.sender_distribution.to_string()),
+            receiver_distribution: Some(
+                args.account_distribution.receiver_distribution.to_string(),
+            ),
+            finality_latency,
+            tx_status,
+            tx_lifecycle,
+            verification,
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
     }
 
-    // Ensure all rpc responses are handled.
-    response_handler_task
-        .await
-        .expect("response handler tasks should succeed");
+    if verification_failed {
+        anyhow::bail!("state after the benchmark does not match the transactions sent");
+    }
 
     Ok(())
 }
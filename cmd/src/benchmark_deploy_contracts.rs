@@ -0,0 +1,178 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Args;
+use log::info;
+use near_ops::account::accounts_from_dir;
+use near_ops::block_service::BlockService;
+use near_ops::contract::read_wasm_bytes;
+use near_ops::deploy::{with_unique_custom_section, DeployReport, DeployResult, BUNDLED_WASM};
+use near_ops::finality::FinalityLatencyReport;
+use near_ops::gas::TxCost;
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::report::BenchmarkReport;
+use near_ops::rpc::{is_executed, parse_tx_execution_status};
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::SendLoop;
+use near_primitives::action::{Action, DeployContractAction};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::views::TxExecutionStatus;
+
+#[derive(Args, Debug)]
+pub struct BenchmarkDeployContractsArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Accounts to which contracts are deployed, picked round robin. Each account must hold
+    /// enough balance to stake storage for the largest contract.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_deploys: u64,
+    /// Contracts to deploy, picked round robin. Pass multiple files to compare code sizes. If not
+    /// provided, the bundled `fungible_token.wasm` is deployed.
+    #[arg(long)]
+    pub wasm_path: Vec<PathBuf>,
+    /// Make the code of every deployment distinct, also from deployments of previous runs, by
+    /// appending a custom section. Otherwise the node may reuse contracts it compiled before.
+    #[arg(long)]
+    pub unique_code: bool,
+    /// Status the RPC waits for before responding to a transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` to measure the time it takes to execute deployments.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
+    #[arg(long)]
+    pub report_path: Option<PathBuf>,
+}
+
+/// Accounts deploy contracts to themselves, each deployment in its own transaction.
+pub async fn benchmark_deploy_contracts(args: &BenchmarkDeployContractsArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
+    let wasms: Vec<Vec<u8>> = if args.wasm_path.is_empty() {
+        vec![BUNDLED_WASM.to_vec()]
+    } else {
+        args.wasm_path
+            .iter()
+            .map(read_wasm_bytes)
+            .collect::<anyhow::Result<_>>()?
+    };
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    assert!(!accounts.is_empty());
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+    let instrumentation = Instrumentation::start(
+        &client,
+        &args.instrumentation,
+        Some(block_service.clone()),
+        args.send.interval(),
+    )
+    .await?;
+
+    // Distinguishes the code of this run from code deployed by previous runs.
+    let run_id = u64::try_from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos())?;
+    if args.unique_code {
+        info!("Deploying unique code with run id {run_id}");
+    }
+
+    let send_loop = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_finality_recording();
+    let (send_duration, results) = send_loop
+        .run_tagged(
+            args.num_deploys,
+            |i| {
+                let idx_account =
+                    usize::try_from(i % u64::try_from(accounts.len()).unwrap()).unwrap();
+                let wasm =
+                    &wasms[usize::try_from(i % u64::try_from(wasms.len()).unwrap()).unwrap()];
+                let code = if args.unique_code {
+                    with_unique_custom_section(wasm, run_id, i)
+                } else {
+                    wasm.clone()
+                };
+                let code_size = code.len();
+
+                let account = &mut accounts[idx_account];
+                let (signer, nonce) = account.next_signer_and_nonce();
+                let transaction = SignedTransaction::from_actions(
+                    nonce,
+                    account.id.clone(),
+                    account.id.clone(),
+                    &signer,
+                    vec![Action::DeployContract(DeployContractAction { code })],
+                    block_service.get_block_hash(),
+                    0,
+                );
+                Ok((transaction, code_size))
+            },
+            |mut responses| async move {
+                let mut results = vec![];
+                while let Some((code_size, response)) = responses.recv().await {
+                    results.push(DeployResult {
+                        code_size,
+                        result: response.result,
+                        finality_timings: response
+                            .finality_timings
+                            .expect("finality is recorded unless wait_until is NONE"),
+                    });
+                }
+                results
+            },
+        )
+        .await?;
+
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+
+    let deploy = DeployReport::new(&results, args.unique_code);
+    deploy.log_summary();
+    let tx_costs: Vec<TxCost> = results
+        .iter()
+        .filter_map(|result| result.result.as_ref().ok())
+        .filter_map(TxCost::from_response)
+        .collect();
+    let finality_latency = FinalityLatencyReport::new(
+        results
+            .into_iter()
+            .map(|result| result.finality_timings)
+            .collect(),
+    );
+    finality_latency.log_summary();
+
+    let instrumentation = instrumentation.finish(&tx_costs).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(args.num_deploys),
+            send_duration,
+            wait_until: Some(args.wait_until.clone()),
+            connection_stats,
+            finality_latency: Some(finality_latency),
+            deploy: Some(deploy),
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
+    }
+
+    Ok(())
+}
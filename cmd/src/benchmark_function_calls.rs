@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Args;
+use log::info;
+use near_ops::account::accounts_from_dir;
+use near_ops::block_service::BlockService;
+use near_ops::contract::ArgsTemplate;
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::report::BenchmarkReport;
+use near_ops::rng::SeedArgs;
+use near_ops::rpc::parse_tx_execution_status;
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::{SendLoop, SendLoopReport};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+use near_primitives::views::TxExecutionStatus;
+
+#[derive(Args, Debug)]
+pub struct BenchmarkFunctionCallsArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Directory with the data of accounts sending the function calls.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_calls: u64,
+    /// Comma separated contracts that are called, round robin.
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub receiver_id: Vec<AccountId>,
+    #[arg(long)]
+    pub method_name: String,
+    /// Template of a json object passed as arguments. The placeholders `{sender}`, `{receiver}`,
+    /// `{random_u64}` and `{i}` are substituted for each call, e.g.
+    /// `{"receiver_id": "{sender}", "amount": "{random_u64}"}`.
+    #[arg(long, default_value = "{}")]
+    pub args: String,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+    #[arg(long)]
+    pub gas: u64,
+    #[arg(long, default_value_t = 0)]
+    pub deposit: u128,
+    /// Sign calls with function call access keys that permit calling `method_name` on the
+    /// receivers, see the `add-keys` command. Every account must have such keys. Otherwise calls
+    /// are signed with full access keys.
+    #[arg(long)]
+    pub use_function_call_keys: bool,
+    /// Status the RPC waits for before responding to a transaction. Unless it is `NONE`, the time
+    /// each transaction takes to reach `INCLUDED`, `EXECUTED_OPTIMISTIC` and `FINAL` (up to
+    /// `wait_until`) is recorded.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "NONE")]
+    pub wait_until: TxExecutionStatus,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
+    #[arg(long)]
+    pub report_path: Option<PathBuf>,
+}
+
+pub async fn benchmark_function_calls(args: &BenchmarkFunctionCallsArgs) -> anyhow::Result<()> {
+    // Function call access keys cannot sign calls that attach a deposit.
+    anyhow::ensure!(
+        !args.use_function_call_keys || args.deposit == 0,
+        "--use-function-call-keys requires --deposit 0"
+    );
+    let args_template = ArgsTemplate::new(args.args.clone())?;
+    let (mut rng, seed) = args.seed.rng();
+
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    assert!(!accounts.is_empty());
+    // Checked upfront, since failing while sending would abort the run midway.
+    if args.use_function_call_keys {
+        for account in accounts.iter() {
+            for receiver_id in args.receiver_id.iter() {
+                anyhow::ensure!(
+                    account.has_function_call_key(receiver_id, &args.method_name),
+                    "{} has no function call key permitting the call on {}, see `add-keys`",
+                    account.id,
+                    receiver_id
+                );
+            }
+        }
+    }
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+    let instrumentation = Instrumentation::start(
+        &client,
+        &args.instrumentation,
+        Some(block_service.clone()),
+        args.send.interval(),
+    )
+    .await?;
+
+    let send_loop = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_finality_recording();
+    let SendLoopReport {
+        send_duration,
+        finality_latency,
+        tx_costs,
+    } = send_loop
+        .run(args.num_calls, |i| {
+            let idx_sender = usize::try_from(i % u64::try_from(accounts.len()).unwrap()).unwrap();
+            let receiver_id = &args.receiver_id
+                [usize::try_from(i % u64::try_from(args.receiver_id.len()).unwrap()).unwrap()];
+            let sender = &mut accounts[idx_sender];
+            let (signer, nonce) = if args.use_function_call_keys {
+                sender
+                    .next_function_call_signer_and_nonce(receiver_id, &args.method_name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{} has no function call key permitting the call on {}, see `add-keys`",
+                            sender.id,
+                            receiver_id
+                        )
+                    })?
+            } else {
+                sender.next_signer_and_nonce()
+            };
+            Ok(SignedTransaction::call(
+                nonce,
+                sender.id.clone(),
+                receiver_id.clone(),
+                &signer,
+                args.deposit,
+                args.method_name.clone(),
+                args_template
+                    .render(&sender.id, receiver_id, i, &mut rng)
+                    .into_bytes(),
+                args.gas,
+                block_service.get_block_hash(),
+            ))
+        })
+        .await?;
+
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+
+    let instrumentation = instrumentation.finish(&tx_costs).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(args.num_calls),
+            send_duration,
+            wait_until: Some(args.wait_until.clone()),
+            seed: Some(seed),
+            connection_stats,
+            finality_latency,
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{Args, ValueEnum};
+use log::info;
+use near_ops::account::accounts_from_dir;
+use near_ops::block_service::BlockService;
+use near_ops::finality::FinalityLatencyReport;
+use near_ops::gas::TxCost;
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::meta_tx::{new_signed_delegate_action, MetaTxReport, MetaTxResult};
+use near_ops::report::BenchmarkReport;
+use near_ops::rpc::{is_executed, parse_tx_execution_status};
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::SendLoop;
+use near_primitives::action::{Action, FunctionCallAction, TransferAction};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+use near_primitives::views::TxExecutionStatus;
+
+#[derive(Args, Debug)]
+pub struct BenchmarkMetaTransactionsArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Directory with the data of users signing delegate actions.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    /// Directory with the data of relayers that submit delegate actions and pay for gas. Relayers
+    /// rotate through their keys, see `add-keys`.
+    #[arg(long)]
+    pub relayer_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_txs: u64,
+    /// Action wrapped in delegate actions.
+    #[arg(long, value_enum, default_value_t = InnerAction::Transfer)]
+    pub inner_action: InnerAction,
+    /// With `--inner-action transfer`, the amount each user sends to the next user.
+    #[arg(long, default_value_t = 1)]
+    pub amount: u128,
+    /// With `--inner-action function-call`, the contract that is called.
+    #[arg(long, required_if_eq("inner_action", "function-call"))]
+    pub receiver_id: Option<AccountId>,
+    #[arg(long, required_if_eq("inner_action", "function-call"))]
+    pub method_name: Option<String>,
+    /// A string that represents a json object.
+    #[arg(long, default_value = "{}")]
+    pub args: String,
+    #[arg(long, default_value_t = 30_000_000_000_000)]
+    pub gas: u64,
+    /// Delegate actions expire this many blocks after the latest block seen when signing them.
+    #[arg(long, default_value_t = 100)]
+    pub max_block_height_delta: u64,
+    /// Status the RPC waits for before responding to a transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` to measure the cost of executing delegate actions.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
+    #[arg(long)]
+    pub report_path: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+pub enum InnerAction {
+    Transfer,
+    FunctionCall,
+}
+
+/// Users sign delegate actions which relayers, picked round robin, submit in transactions.
+pub async fn benchmark_meta_transactions(
+    args: &BenchmarkMetaTransactionsArgs,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
+    let mut users = accounts_from_dir(&args.user_data_dir)?;
+    let mut relayers = accounts_from_dir(&args.relayer_data_dir)?;
+    assert!(users.len() >= 2);
+    assert!(!relayers.is_empty());
+    let relayer_ids: Vec<AccountId> = relayers.iter().map(|relayer| relayer.id.clone()).collect();
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+    let instrumentation = Instrumentation::start(
+        &client,
+        &args.instrumentation,
+        Some(block_service.clone()),
+        args.send.interval(),
+    )
+    .await?;
+
+    let send_loop = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_finality_recording();
+    let (send_duration, results) = send_loop
+        .run_tagged(
+            args.num_txs,
+            |i| {
+                let idx_user = usize::try_from(i % u64::try_from(users.len()).unwrap()).unwrap();
+                let idx_relayer =
+                    usize::try_from(i % u64::try_from(relayers.len()).unwrap()).unwrap();
+
+                let (receiver_id, inner_action) = match args.inner_action {
+                    InnerAction::Transfer => {
+                        // Send to the next user to avoid transfers to self.
+                        let receiver_id = users[(idx_user + 1) % users.len()].id.clone();
+                        let action = Action::Transfer(TransferAction {
+                            deposit: args.amount,
+                        });
+                        (receiver_id, action)
+                    }
+                    InnerAction::FunctionCall => {
+                        let action = Action::FunctionCall(Box::new(FunctionCallAction {
+                            method_name: args.method_name.clone().unwrap(),
+                            args: args.args.clone().into_bytes(),
+                            gas: args.gas,
+                            deposit: 0,
+                        }));
+                        (args.receiver_id.clone().unwrap(), action)
+                    }
+                };
+
+                let user = &mut users[idx_user];
+                let (user_signer, user_nonce) = user.next_signer_and_nonce();
+                let signed_delegate_action = new_signed_delegate_action(
+                    user.id.clone(),
+                    receiver_id,
+                    vec![inner_action],
+                    user_nonce,
+                    block_service.get_block_height() + args.max_block_height_delta,
+                    &user_signer,
+                )?;
+
+                let relayer = &mut relayers[idx_relayer];
+                let (relayer_signer, relayer_nonce) = relayer.next_signer_and_nonce();
+                let transaction = SignedTransaction::from_actions(
+                    relayer_nonce,
+                    relayer.id.clone(),
+                    user.id.clone(),
+                    &relayer_signer,
+                    vec![Action::Delegate(Box::new(signed_delegate_action))],
+                    block_service.get_block_hash(),
+                    0,
+                );
+                Ok((transaction, idx_relayer))
+            },
+            |mut responses| async move {
+                let mut results = vec![];
+                while let Some((relayer_idx, response)) = responses.recv().await {
+                    results.push(MetaTxResult {
+                        relayer_idx,
+                        result: response.result,
+                        finality_timings: response
+                            .finality_timings
+                            .expect("finality is recorded unless wait_until is NONE"),
+                    });
+                }
+                results
+            },
+        )
+        .await?;
+
+    for account in users.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+    for account in relayers.iter() {
+        account.write_to_dir(&args.relayer_data_dir)?;
+    }
+
+    let meta_tx = MetaTxReport::new(&relayer_ids, &results);
+    meta_tx.log_summary();
+    let tx_costs: Vec<TxCost> = results
+        .iter()
+        .filter_map(|result| result.result.as_ref().ok())
+        .filter_map(TxCost::from_response)
+        .collect();
+    let finality_latency = FinalityLatencyReport::new(
+        results
+            .into_iter()
+            .map(|result| result.finality_timings)
+            .collect(),
+    );
+    finality_latency.log_summary();
+
+    let instrumentation = instrumentation.finish(&tx_costs).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(args.num_txs),
+            send_duration,
+            wait_until: Some(args.wait_until.clone()),
+            connection_stats,
+            finality_latency: Some(finality_latency),
+            meta_tx: Some(meta_tx),
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
+    }
+
+    Ok(())
+}
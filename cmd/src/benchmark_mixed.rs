@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Args;
+use log::info;
+use near_ops::account::accounts_from_dir;
+use near_ops::block_service::BlockService;
+use near_ops::distribution::{AccountDistributionArgs, AccountPairSampler};
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::report::BenchmarkReport;
+use near_ops::rng::SeedArgs;
+use near_ops::rpc::{is_executed, is_transaction_and_receipts_success, parse_tx_execution_status};
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::{SendLoop, SendLoopReport};
+use near_ops::workload::{Workload, WorkloadArgs, WorkloadReport};
+use near_primitives::views::TxExecutionStatus;
+
+#[derive(Args, Debug)]
+pub struct BenchmarkMixedArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_txs: u64,
+    #[command(flatten)]
+    pub workload: WorkloadArgs,
+    #[command(flatten)]
+    pub account_distribution: AccountDistributionArgs,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+    /// Directory where data of accounts successfully created by `create-account` transactions is
+    /// stored, e.g. to delete them later. If not provided, their keys are discarded. Requires
+    /// `wait_until` to be at least `EXECUTED_OPTIMISTIC`.
+    #[arg(long)]
+    pub created_accounts_dir: Option<PathBuf>,
+    /// Status the RPC waits for before responding to a transaction. Unless it is `NONE`, the time
+    /// each transaction takes to reach `INCLUDED`, `EXECUTED_OPTIMISTIC` and `FINAL` (up to
+    /// `wait_until`) is recorded.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "NONE")]
+    pub wait_until: TxExecutionStatus,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
+    #[arg(long)]
+    pub report_path: Option<PathBuf>,
+}
+
+pub async fn benchmark_mixed(args: &BenchmarkMixedArgs) -> anyhow::Result<()> {
+    // Only accounts whose creation is known to have succeeded are stored.
+    anyhow::ensure!(
+        args.created_accounts_dir.is_none() || is_executed(&args.wait_until),
+        "--created-accounts-dir requires --wait-until to be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
+    // Distinguishes accounts created by this run from those created by previous runs.
+    let run_id = u64::try_from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis())?;
+    let workload = Workload::new(&args.workload, run_id)?;
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    assert!(accounts.len() >= 2);
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+    let instrumentation = Instrumentation::start(
+        &client,
+        &args.instrumentation,
+        Some(block_service.clone()),
+        args.send.interval(),
+    )
+    .await?;
+
+    let mut account_pairs = AccountPairSampler::new(&args.account_distribution, accounts.len());
+    let (mut rng, seed) = args.seed.rng();
+
+    let mut workload_report = WorkloadReport::new(workload.mix());
+    let send_loop = SendLoop::new(
+        client.clone(),
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        args.wait_until.clone(),
+    )
+    .with_finality_recording();
+    let mut rpc_response_handler = send_loop.response_handler();
+    let (send_duration, (rpc_response_handler, created_accounts)) = send_loop
+        .run_tagged(
+            args.num_txs,
+            |i| {
+                let (idx_sender, idx_receiver) = account_pairs.sample(&mut rng);
+                let receiver_id = accounts[idx_receiver].id.clone();
+                let workload_tx = workload.new_transaction(
+                    &mut accounts[idx_sender],
+                    &receiver_id,
+                    i,
+                    block_service.get_block_hash(),
+                    &mut rng,
+                )?;
+                workload_report.record_sent(workload_tx.kind);
+                Ok((workload_tx.transaction, workload_tx.new_account))
+            },
+            |mut responses| async move {
+                let mut created_accounts = vec![];
+                let mut num_received = 0;
+                while let Some((new_account, response)) = responses.recv().await {
+                    num_received += 1;
+                    if let Some(new_account) = new_account {
+                        if response
+                            .result
+                            .as_ref()
+                            .is_ok_and(is_transaction_and_receipts_success)
+                        {
+                            created_accounts.push(new_account);
+                        }
+                    }
+                    rpc_response_handler.handle_response(response);
+                }
+                rpc_response_handler.log_transport_errors(num_received);
+                (rpc_response_handler, created_accounts)
+            },
+        )
+        .await?;
+    let SendLoopReport {
+        send_duration,
+        finality_latency,
+        tx_costs,
+    } = SendLoopReport::new(send_duration, rpc_response_handler);
+    workload_report.log_summary();
+
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+    if let Some(dir) = &args.created_accounts_dir {
+        // Nonces of new access keys are set by nearcore, see `create-sub-accounts`. Nonces of
+        // these accounts must be queried before sending transactions from them.
+        info!(
+            "Storing {} successfully created accounts",
+            created_accounts.len()
+        );
+        for account in created_accounts.iter() {
+            account.write_to_dir(dir)?;
+        }
+    }
+
+    let instrumentation = instrumentation.finish(&tx_costs).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(args.num_txs),
+            send_duration,
+            wait_until: Some(args.wait_until.clone()),
+            seed: Some(seed),
+            connection_stats,
+            sender_distribution: Some(args.account_distribution.sender_distribution.to_string()),
+            receiver_distribution: Some(
+                args.account_distribution.receiver_distribution.to_string(),
+            ),
+            finality_latency,
+            workload: Some(workload_report),
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Args;
+use log::info;
+use near_ops::account::accounts_from_dir;
+use near_ops::instrumentation::{Instrumentation, InstrumentationArgs};
+use near_ops::load_metrics::load_metrics;
+use near_ops::query::{QueryArgs, QueryGenerator, QueryReport, QueryResult};
+use near_ops::report::BenchmarkReport;
+use near_ops::rng::SeedArgs;
+use near_ops::rpc_client::{RpcClient, RpcClientArgs, SendArgs};
+use near_ops::send_loop::send_paced;
+use rand::Rng;
+
+#[derive(Args, Debug)]
+pub struct BenchmarkQueriesArgs {
+    #[command(flatten)]
+    pub send: SendArgs,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
+    /// Accounts that are queried. Their keys are used only for `view-access-key` queries.
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_queries: u64,
+    #[command(flatten)]
+    pub queries: QueryArgs,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+    #[command(flatten)]
+    pub instrumentation: InstrumentationArgs,
+    /// If provided, a json report of the run is written to this file.
+    #[arg(long)]
+    pub report_path: Option<PathBuf>,
+}
+
+pub async fn benchmark_queries(args: &BenchmarkQueriesArgs) -> anyhow::Result<()> {
+    let generator = QueryGenerator::new(&args.queries)?;
+    let accounts = accounts_from_dir(&args.user_data_dir)?;
+    assert!(!accounts.is_empty());
+    let (mut rng, seed) = args.seed.rng();
+
+    let client = RpcClient::new(&args.send.rpc_url, &args.rpc_client)?;
+    // Queries need no recent block hash, so a block service is started only for the dashboard.
+    let instrumentation =
+        Instrumentation::start(&client, &args.instrumentation, None, args.send.interval()).await?;
+
+    let (send_duration, results) = send_paced(
+        args.num_queries,
+        args.send.interval(),
+        args.send.channel_buffer_size,
+        |i| {
+            let account = &accounts[rng.gen_range(0..accounts.len())];
+            let query = generator.new_query(account, i, &mut rng);
+            let client = client.clone();
+            Ok(async move {
+                let kind = query.kind;
+                let started = Instant::now();
+                let in_flight = load_metrics().start_request();
+                // Errors are summarized in the report instead of being logged one by one.
+                let (outcome, error) = query.send(&client).await;
+                let latency = started.elapsed();
+                in_flight.finish(outcome);
+                QueryResult {
+                    kind,
+                    latency,
+                    error,
+                }
+            })
+        },
+        |mut receiver| async move {
+            let mut results = vec![];
+            while let Some(result) = receiver.recv().await {
+                results.push(result);
+            }
+            results
+        },
+    )
+    .await?;
+
+    let queries = QueryReport::new(generator.mix(), &results);
+    queries.log_summary();
+
+    let instrumentation = instrumentation.finish(&[]).await?;
+
+    let connection_stats = client.connection_stats();
+    info!("RPC connections: {}", connection_stats);
+
+    if let Some(report_path) = &args.report_path {
+        let report = BenchmarkReport {
+            send_duration,
+            seed: Some(seed),
+            connection_stats,
+            queries: Some(queries),
+            ..instrumentation.into()
+        };
+        report.write_to_file(report_path)?;
+        info!("Wrote report to {:?}", report_path);
+    }
+
+    Ok(())
+}
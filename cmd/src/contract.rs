@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::Args;
 use near_crypto::{InMemorySigner, KeyType, SecretKey, Signer};
-use near_jsonrpc_client::{methods::send_tx::RpcSendTransactionRequest, JsonRpcClient};
+use near_jsonrpc_client::methods::send_tx::RpcSendTransactionRequest;
 use near_primitives::{
     transaction::SignedTransaction,
     types::{AccountId, BlockReference, Finality},
@@ -10,16 +10,21 @@ use near_primitives::{
 };
 
 use near_ops::{
-    account::Account,
+    account::{parse_key_type, Account},
     contract::read_wasm_bytes,
-    rpc::{assert_transaction_and_receipts_success, get_block},
+    rpc::{
+        assert_transaction_and_receipts_success, get_block, is_executed, parse_tx_execution_status,
+    },
+    rpc_client::{RpcClient, RpcClientArgs},
 };
 
 #[derive(Args, Debug)]
 pub struct CreateContractArgs {
-    /// TODO try to have single arg for all commands
+    /// Address of the RPC node, e.g. `http://localhost:3030`.
     #[arg(long)]
     pub rpc_url: String,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
     #[arg(long)]
     pub signer_key_path: PathBuf,
     /// Starting nonce > current_nonce to send transactions to create sub accounts.
@@ -36,12 +41,24 @@ pub struct CreateContractArgs {
     pub user_data_dir: PathBuf,
     #[arg(long)]
     pub wasm_path: PathBuf,
+    /// Type of the new account's key: `ed25519` or `secp256k1`.
+    #[arg(long, value_parser = parse_key_type, default_value = "ed25519")]
+    pub key_type: KeyType,
+    /// Status the RPC waits for before responding to the transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` to verify the transaction succeeded.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
 }
 
 pub async fn create_contract(args: &CreateContractArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
     let signer = InMemorySigner::from_file(&args.signer_key_path)?;
 
-    let client = JsonRpcClient::connect(&args.rpc_url);
+    let client = RpcClient::new(&args.rpc_url, &args.rpc_client)?;
     // The block hash included in a transaction affects the duration for which it is valid.
     // Benchmarks are expected to run ~30-60 minutes. Hence using any recent hash should be
     // sufficient to create valid transactions.
@@ -50,7 +67,7 @@ pub async fn create_contract(args: &CreateContractArgs) -> anyhow::Result<()> {
         .header
         .hash;
 
-    let sub_account_key = SecretKey::from_random(KeyType::ED25519);
+    let sub_account_key = SecretKey::from_random(args.key_type);
 
     let transaction = SignedTransaction::create_contract(
         args.nonce,
@@ -63,11 +80,12 @@ pub async fn create_contract(args: &CreateContractArgs) -> anyhow::Result<()> {
         latest_block_hash,
     );
 
-    let request = RpcSendTransactionRequest {
-        signed_transaction: transaction,
-        wait_until: TxExecutionStatus::ExecutedOptimistic,
-    };
-    let response = client.call(request).await?;
+    let response = client
+        .call(RpcSendTransactionRequest {
+            signed_transaction: transaction,
+            wait_until: args.wait_until.clone(),
+        })
+        .await?;
     assert_transaction_and_receipts_success(&response);
 
     let account = Account::new(args.new_account_id.clone(), sub_account_key, 0);
@@ -78,9 +96,11 @@ pub async fn create_contract(args: &CreateContractArgs) -> anyhow::Result<()> {
 
 #[derive(Args, Debug)]
 pub struct CallContractArgs {
-    /// TODO try to have single arg for all commands
+    /// Address of the RPC node, e.g. `http://localhost:3030`.
     #[arg(long)]
     pub rpc_url: String,
+    #[command(flatten)]
+    pub rpc_client: RpcClientArgs,
     #[arg(long)]
     pub signer_key_path: PathBuf,
     /// Starting nonce > current_nonce to send transactions to create sub accounts.
@@ -91,19 +111,28 @@ pub struct CallContractArgs {
     pub receiver_id: AccountId,
     #[arg(long)]
     pub method_name: String,
-    /// A that represents a json object.
+    /// A string that represents a json object.
     #[arg(long)]
     pub args: String,
     #[arg(long)]
     pub gas: u64,
     #[arg(long)]
     pub deposit: u128,
+    /// Status the RPC waits for before responding to the transaction. Must be at least
+    /// `EXECUTED_OPTIMISTIC` to verify the transaction succeeded.
+    #[arg(long, value_parser = parse_tx_execution_status, default_value = "EXECUTED_OPTIMISTIC")]
+    pub wait_until: TxExecutionStatus,
 }
 
 pub async fn call_contract(args: &CallContractArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_executed(&args.wait_until),
+        "--wait-until must be at least EXECUTED_OPTIMISTIC, got {:?}",
+        args.wait_until
+    );
     let signer = InMemorySigner::from_file(&args.signer_key_path)?;
 
-    let client = JsonRpcClient::connect(&args.rpc_url);
+    let client = RpcClient::new(&args.rpc_url, &args.rpc_client)?;
     // The block hash included in a transaction affects the duration for which it is valid.
     // Benchmarks are expected to run ~30-60 minutes. Hence using any recent hash should be
     // sufficient to create valid transactions.
@@ -116,7 +145,10 @@ pub async fn call_contract(args: &CallContractArgs) -> anyhow::Result<()> {
 
     // Validate args.args is string that represents a json object.
     let args_json: serde_json::Value = serde_json::from_str(&args.args)?;
-    matches!(args_json, serde_json::Value::Object(_));
+    anyhow::ensure!(
+        args_json.is_object(),
+        "--args must be a json object, got {args_json}"
+    );
     let function_args = args.args.clone().into_bytes();
 
     let transaction = SignedTransaction::call(
@@ -131,11 +163,12 @@ pub async fn call_contract(args: &CallContractArgs) -> anyhow::Result<()> {
         latest_block_hash,
     );
 
-    let request = RpcSendTransactionRequest {
-        signed_transaction: transaction,
-        wait_until: TxExecutionStatus::ExecutedOptimistic,
-    };
-    let response = client.call(request).await?;
+    let response = client
+        .call(RpcSendTransactionRequest {
+            signed_transaction: transaction,
+            wait_until: args.wait_until.clone(),
+        })
+        .await?;
     assert_transaction_and_receipts_success(&response);
 
     Ok(())
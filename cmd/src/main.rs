@@ -1,13 +1,31 @@
+use std::net::SocketAddr;
+
 use benchmark::{benchmark_native_transfers, BenchmarkNativeTransferArgs};
 use clap::{Parser, Subcommand};
 
 use near_ops::account::CreateAccountArgs;
+use near_ops::load_metrics::serve_load_metrics;
 
 mod account;
-use account::{create_sub_accounts, CreateSubAccountsArgs};
+use account::{
+    add_keys, create_sub_accounts, delete_accounts, refill_accounts, AddKeysArgs,
+    CreateSubAccountsArgs, DeleteAccountsArgs, RefillAccountsArgs,
+};
 mod benchmark;
+mod benchmark_deploy_contracts;
+use benchmark_deploy_contracts::{benchmark_deploy_contracts, BenchmarkDeployContractsArgs};
+mod benchmark_function_calls;
+use benchmark_function_calls::{benchmark_function_calls, BenchmarkFunctionCallsArgs};
+mod benchmark_meta_transactions;
+mod benchmark_mixed;
+mod benchmark_queries;
+use benchmark_meta_transactions::{benchmark_meta_transactions, BenchmarkMetaTransactionsArgs};
+use benchmark_mixed::{benchmark_mixed, BenchmarkMixedArgs};
+use benchmark_queries::{benchmark_queries, BenchmarkQueriesArgs};
 mod contract;
 use contract::{call_contract, create_contract, CallContractArgs, CreateContractArgs};
+mod report;
+use report::{render_report, RenderReportArgs};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -15,6 +33,10 @@ use contract::{call_contract, create_contract, CallContractArgs, CreateContractA
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// If provided, metrics of the generated load are served on `http://<addr>/metrics`, e.g.
+    /// `0.0.0.0:9464`, to be scraped by Prometheus.
+    #[arg(long, global = true)]
+    metrics_addr: Option<SocketAddr>,
 }
 
 #[derive(Subcommand)]
@@ -23,10 +45,30 @@ enum Commands {
     CreateAccount(CreateAccountArgs),
     /// Creates sub accounts for the signer.
     CreateSubAccounts(CreateSubAccountsArgs),
+    /// Tops up accounts whose balance dropped below a threshold.
+    RefillAccounts(RefillAccountsArgs),
+    /// Deletes accounts, transferring their balance to the signer.
+    DeleteAccounts(DeleteAccountsArgs),
+    /// Adds access keys to accounts.
+    AddKeys(AddKeysArgs),
     /// Creates a sub account of the signer and deploys a contract to it.
     CreateContract(CreateContractArgs),
     CallContract(CallContractArgs),
     BenchmarkNativeTransfers(BenchmarkNativeTransferArgs),
+    /// Sends function calls with templated arguments from accounts, optionally signed with function
+    /// call access keys.
+    BenchmarkFunctionCalls(BenchmarkFunctionCallsArgs),
+    /// Sends delegate actions signed by users via relayers.
+    BenchmarkMetaTransactions(BenchmarkMetaTransactionsArgs),
+    /// Sends a weighted mix of transaction kinds.
+    BenchmarkMixed(Box<BenchmarkMixedArgs>),
+    /// Sends read requests like `view_account`, `view_access_key`, view function calls and
+    /// `block` in a weighted mix.
+    BenchmarkQueries(BenchmarkQueriesArgs),
+    /// Deploys contracts to many accounts concurrently.
+    BenchmarkDeployContracts(BenchmarkDeployContractsArgs),
+    /// Renders a json benchmark report as a self-contained HTML page with charts.
+    RenderReport(RenderReportArgs),
 }
 
 #[tokio::main]
@@ -36,6 +78,10 @@ async fn main() -> anyhow::Result<()> {
 
     // TODO increase file descriptor limit, if required.
 
+    if let Some(addr) = cli.metrics_addr {
+        serve_load_metrics(addr).await?;
+    }
+
     match &cli.command {
         Commands::CreateAccount(CreateAccountArgs { .. }) => {
             unimplemented!();
@@ -43,6 +89,15 @@ async fn main() -> anyhow::Result<()> {
         Commands::CreateSubAccounts(args) => {
             create_sub_accounts(args).await?;
         }
+        Commands::RefillAccounts(args) => {
+            refill_accounts(args).await?;
+        }
+        Commands::DeleteAccounts(args) => {
+            delete_accounts(args).await?;
+        }
+        Commands::AddKeys(args) => {
+            add_keys(args).await?;
+        }
         Commands::CreateContract(args) => {
             create_contract(args).await?;
         }
@@ -52,6 +107,24 @@ async fn main() -> anyhow::Result<()> {
         Commands::BenchmarkNativeTransfers(args) => {
             benchmark_native_transfers(args).await?;
         }
+        Commands::BenchmarkFunctionCalls(args) => {
+            benchmark_function_calls(args).await?;
+        }
+        Commands::BenchmarkMetaTransactions(args) => {
+            benchmark_meta_transactions(args).await?;
+        }
+        Commands::BenchmarkMixed(args) => {
+            benchmark_mixed(args).await?;
+        }
+        Commands::BenchmarkQueries(args) => {
+            benchmark_queries(args).await?;
+        }
+        Commands::BenchmarkDeployContracts(args) => {
+            benchmark_deploy_contracts(args).await?;
+        }
+        Commands::RenderReport(args) => {
+            render_report(args)?;
+        }
     }
     Ok(())
 }
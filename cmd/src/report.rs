@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Args;
+use log::info;
+use near_ops::html_report::render_html;
+use near_ops::report::BenchmarkReport;
+
+#[derive(Args, Debug)]
+pub struct RenderReportArgs {
+    /// Json report written by a benchmark with `--report-path`.
+    #[arg(long)]
+    pub report_path: PathBuf,
+    /// Where the HTML page is written. Defaults to the report path with extension `html`.
+    #[arg(long)]
+    pub output_path: Option<PathBuf>,
+    /// Heading of the page. Defaults to the file name of the report.
+    #[arg(long)]
+    pub title: Option<String>,
+}
+
+pub fn render_report(args: &RenderReportArgs) -> anyhow::Result<()> {
+    let report = BenchmarkReport::from_file(&args.report_path)?;
+    let title = match &args.title {
+        Some(title) => title.clone(),
+        None => args
+            .report_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let output_path = args
+        .output_path
+        .clone()
+        .unwrap_or_else(|| args.report_path.with_extension("html"));
+    fs::write(&output_path, render_html(&report, &title))?;
+    info!("Wrote HTML report to {:?}", output_path);
+    Ok(())
+}
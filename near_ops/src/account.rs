@@ -1,15 +1,16 @@
 use std::{
     fs::{self},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use clap::Args;
-use near_crypto::{InMemorySigner, PublicKey, SecretKey, Signer};
+use near_crypto::{InMemorySigner, KeyType, PublicKey, SecretKey, Signer};
 use near_primitives::{
-    account::{AccessKey, AccessKeyPermission},
+    account::{AccessKey, AccessKeyPermission, FunctionCallPermission},
     action::{Action, AddKeyAction, CreateAccountAction, TransferAction},
     types::AccountId,
 };
+use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
 
 #[derive(Args, Debug)]
@@ -22,17 +23,117 @@ pub struct CreateAccountArgs {
 pub fn new_create_subaccount_actions(public_key: PublicKey, deposit: u128) -> Vec<Action> {
     vec![
         Action::CreateAccount(CreateAccountAction {}),
-        Action::AddKey(Box::new(AddKeyAction {
-            access_key: AccessKey {
-                nonce: 0, // This value will be ignored: https://github.com/near/nearcore/pull/4064
-                permission: AccessKeyPermission::FullAccess,
-            },
-            public_key,
-        })),
+        new_add_full_access_key_action(public_key),
         Action::Transfer(TransferAction { deposit }),
     ]
 }
 
+pub fn new_add_full_access_key_action(public_key: PublicKey) -> Action {
+    new_add_key_action(public_key, AccessKeyPermission::FullAccess)
+}
+
+pub fn new_add_key_action(public_key: PublicKey, permission: AccessKeyPermission) -> Action {
+    Action::AddKey(Box::new(AddKeyAction {
+        access_key: AccessKey {
+            nonce: 0, // This value will be ignored: https://github.com/near/nearcore/pull/4064
+            permission,
+        },
+        public_key,
+    }))
+}
+
+/// Whether a function call access key with `permission` may sign a call of `method_name` on
+/// `receiver_id`. An empty list of method names allows calling any method.
+pub fn permits_function_call(
+    permission: &FunctionCallPermission,
+    receiver_id: &AccountId,
+    method_name: &str,
+) -> bool {
+    permission.receiver_id == receiver_id.as_str()
+        && (permission.method_names.is_empty()
+            || permission
+                .method_names
+                .iter()
+                .any(|name| name == method_name))
+}
+
+/// Key types of keys generated for new accounts, parsed by [`parse_key_type_selection`].
+///
+/// The key type is part of the serialized keys in [`Account`] files, e.g. `secp256k1:...`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeyTypeSelection {
+    Ed25519,
+    Secp256k1,
+    /// The given fraction of keys is `secp256k1`, the others are `ed25519`.
+    Mix {
+        secp256k1_ratio: f64,
+    },
+}
+
+impl KeyTypeSelection {
+    /// Returns the key type of the `i`-th generated key. With [`Self::Mix`], `secp256k1` keys are
+    /// spread evenly across indices.
+    pub fn key_type(&self, i: u64) -> KeyType {
+        let is_secp256k1 = match self {
+            Self::Ed25519 => false,
+            Self::Secp256k1 => true,
+            Self::Mix { secp256k1_ratio } => {
+                ((i + 1) as f64 * secp256k1_ratio).floor() > (i as f64 * secp256k1_ratio).floor()
+            }
+        };
+        if is_secp256k1 {
+            KeyType::SECP256K1
+        } else {
+            KeyType::ED25519
+        }
+    }
+}
+
+/// Generates a key derived from `rng`, so that runs with the same seed generate the same keys.
+pub fn secret_key_from_rng(key_type: KeyType, rng: &mut impl Rng) -> SecretKey {
+    // Key derivation uses up to 32 bytes of the seed.
+    let seed: String = rng
+        .sample_iter(Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    SecretKey::from_seed(key_type, &seed)
+}
+
+/// Parses a [`KeyTypeSelection`] from `ed25519`, `secp256k1` or `mix:<ratio>` where `<ratio>` is
+/// the fraction of `secp256k1` keys, e.g. `mix:0.25`.
+pub fn parse_key_type_selection(s: &str) -> anyhow::Result<KeyTypeSelection> {
+    match s.to_lowercase().as_str() {
+        "ed25519" => Ok(KeyTypeSelection::Ed25519),
+        "secp256k1" => Ok(KeyTypeSelection::Secp256k1),
+        other => {
+            let ratio = other
+                .strip_prefix("mix:")
+                .and_then(|ratio| ratio.parse::<f64>().ok())
+                .ok_or_else(|| anyhow::anyhow!("invalid key type: {s}"))?;
+            anyhow::ensure!(
+                (0.0..=1.0).contains(&ratio),
+                "secp256k1 ratio must be within [0, 1], got {ratio}"
+            );
+            Ok(KeyTypeSelection::Mix {
+                secp256k1_ratio: ratio,
+            })
+        }
+    }
+}
+
+/// Parses `ed25519` or `secp256k1`, for commands generating a single key where a
+/// [`KeyTypeSelection::Mix`] has no meaning.
+pub fn parse_key_type(s: &str) -> anyhow::Result<KeyType> {
+    match parse_key_type_selection(s)? {
+        KeyTypeSelection::Ed25519 => Ok(KeyType::ED25519),
+        KeyTypeSelection::Secp256k1 => Ok(KeyType::SECP256K1),
+        KeyTypeSelection::Mix { .. } => {
+            anyhow::bail!("expected ed25519 or secp256k1 since a single key is generated, got {s}")
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Account {
     #[serde(rename = "account_id")]
@@ -41,6 +142,57 @@ pub struct Account {
     pub secret_key: SecretKey,
     // New transaction must have a nonce bigger than this.
     pub nonce: u64,
+    /// Access keys in addition to the one above. Each key has its own nonce, so transactions
+    /// signed with different keys do not contend for nonces.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_keys: Vec<AccountKey>,
+    /// Index of the key used by [`Self::next_signer_and_nonce`], with `0` being the key above and
+    /// `i` the `i-1`-th additional key.
+    #[serde(skip)]
+    next_key_idx: usize,
+}
+
+/// An additional access key of an [`Account`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AccountKey {
+    pub public_key: PublicKey,
+    pub secret_key: SecretKey,
+    // New transaction signed with this key must have a nonce bigger than this.
+    pub nonce: u64,
+    /// Restrictions of a function call access key. `None` for full access keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCallPermission>,
+}
+
+impl AccountKey {
+    pub fn new(secret_key: SecretKey, nonce: u64) -> Self {
+        Self {
+            public_key: secret_key.public_key(),
+            secret_key,
+            nonce,
+            function_call: None,
+        }
+    }
+
+    pub fn new_function_call(
+        secret_key: SecretKey,
+        nonce: u64,
+        permission: FunctionCallPermission,
+    ) -> Self {
+        Self {
+            function_call: Some(permission),
+            ..Self::new(secret_key, nonce)
+        }
+    }
+
+    /// Returns the action that adds this key to an account.
+    pub fn add_key_action(&self) -> Action {
+        let permission = match &self.function_call {
+            Some(permission) => AccessKeyPermission::FunctionCall(permission.clone()),
+            None => AccessKeyPermission::FullAccess,
+        };
+        new_add_key_action(self.public_key.clone(), permission)
+    }
 }
 
 impl Account {
@@ -50,6 +202,8 @@ impl Account {
             public_key: secret_key.public_key(),
             secret_key,
             nonce,
+            additional_keys: vec![],
+            next_key_idx: 0,
         }
     }
 
@@ -61,11 +215,20 @@ impl Account {
 
     pub fn write_to_dir(&self, dir: &Path) -> anyhow::Result<()> {
         let json = serde_json::to_string(self)?;
+        fs::write(self.file_path(dir), json)?;
+        Ok(())
+    }
+
+    /// Removes the file written by [`Self::write_to_dir`].
+    pub fn remove_from_dir(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::remove_file(self.file_path(dir))?;
+        Ok(())
+    }
+
+    fn file_path(&self, dir: &Path) -> PathBuf {
         let mut file_name = self.id.to_string();
         file_name.push_str(".json");
-        let file_path = dir.join(file_name);
-        fs::write(file_path, json)?;
-        Ok(())
+        dir.join(file_name)
     }
 
     pub fn as_signer(&self) -> Signer {
@@ -79,9 +242,97 @@ impl Account {
         self.nonce += 1;
         self.nonce
     }
+
+    pub fn num_keys(&self) -> usize {
+        1 + self.additional_keys.len()
+    }
+
+    /// Public keys of all access keys, starting with the one of [`Self::public_key`].
+    pub fn public_keys(&self) -> impl Iterator<Item = &PublicKey> {
+        std::iter::once(&self.public_key).chain(self.additional_keys.iter().map(|k| &k.public_key))
+    }
+
+    /// Local nonces of all access keys, in the order of [`Self::public_keys`].
+    pub fn nonces(&self) -> Vec<u64> {
+        std::iter::once(self.nonce)
+            .chain(self.additional_keys.iter().map(|k| k.nonce))
+            .collect()
+    }
+
+    /// Overwrites the local nonces of all access keys, given in the order of [`Self::public_keys`].
+    pub fn set_nonces(&mut self, nonces: &[u64]) {
+        assert_eq!(nonces.len(), self.num_keys(), "expected one nonce per key");
+        self.nonce = nonces[0];
+        for (key, nonce) in self.additional_keys.iter_mut().zip(&nonces[1..]) {
+            key.nonce = *nonce;
+        }
+    }
+
+    /// Returns a signer and the bumped nonce of the next full access key. Successive calls rotate
+    /// through all full access keys of the account.
+    pub fn next_signer_and_nonce(&mut self) -> (Signer, u64) {
+        self.next_matching_signer_and_nonce(|permission| permission.is_none())
+            .expect("the first key should have full access")
+    }
+
+    /// Like [`Self::next_signer_and_nonce`], but rotates through the function call keys that
+    /// permit calling `method_name` on `receiver_id`. Returns `None` if there is no such key.
+    pub fn next_function_call_signer_and_nonce(
+        &mut self,
+        receiver_id: &AccountId,
+        method_name: &str,
+    ) -> Option<(Signer, u64)> {
+        self.next_matching_signer_and_nonce(|permission| {
+            permission.is_some_and(|permission| {
+                permits_function_call(permission, receiver_id, method_name)
+            })
+        })
+    }
+
+    /// Whether the account has a function call key that permits calling `method_name` on
+    /// `receiver_id`.
+    pub fn has_function_call_key(&self, receiver_id: &AccountId, method_name: &str) -> bool {
+        self.additional_keys.iter().any(|key| {
+            key.function_call.as_ref().is_some_and(|permission| {
+                permits_function_call(permission, receiver_id, method_name)
+            })
+        })
+    }
+
+    fn next_matching_signer_and_nonce(
+        &mut self,
+        matches: impl Fn(Option<&FunctionCallPermission>) -> bool,
+    ) -> Option<(Signer, u64)> {
+        let num_keys = self.num_keys();
+        for offset in 0..num_keys {
+            let idx = (self.next_key_idx + offset) % num_keys;
+            // The first key is a full access key.
+            let permission = match idx {
+                0 => None,
+                _ => self.additional_keys[idx - 1].function_call.as_ref(),
+            };
+            if !matches(permission) {
+                continue;
+            }
+
+            self.next_key_idx = (idx + 1) % num_keys;
+            if idx == 0 {
+                return Some((self.as_signer(), self.get_and_bump_nonce()));
+            }
+            let key = &mut self.additional_keys[idx - 1];
+            key.nonce += 1;
+            let signer = Signer::from(InMemorySigner::from_secret_key(
+                self.id.clone(),
+                key.secret_key.clone(),
+            ));
+            return Some((signer, key.nonce));
+        }
+        None
+    }
 }
 
-/// Tries to deserialize all json files in `dir` as [`Account`].
+/// Tries to deserialize all json files in `dir` as [`Account`]. Accounts are sorted by id, so their
+/// order does not depend on the file system.
 pub fn accounts_from_dir(dir: &Path) -> anyhow::Result<Vec<Account>> {
     if !dir.is_dir() {
         anyhow::bail!("{:?} is not a directory", dir);
@@ -96,12 +347,13 @@ pub fn accounts_from_dir(dir: &Path) -> anyhow::Result<Vec<Account>> {
         }
         let path = entry.path();
         let file_extension = path.extension();
-        if file_extension == None || file_extension.unwrap() != "json" {
+        if file_extension.is_none() || file_extension.unwrap() != "json" {
             continue;
         }
         let account = Account::from_file(&path)?;
         accounts.push(account);
     }
+    accounts.sort_by(|a, b| a.id.cmp(&b.id));
 
     Ok(accounts)
 }
@@ -120,7 +372,123 @@ mod tests {
         transaction::{Transaction, TransactionV0},
     };
 
-    use crate::test_utils::connect_workspaces_to_sandbox;
+    use near_primitives::{account::FunctionCallPermission, types::AccountId};
+
+    use super::{
+        parse_key_type, parse_key_type_selection, secret_key_from_rng, Account, AccountKey,
+        KeyTypeSelection,
+    };
+    use crate::{rng::seeded_rng, test_utils::connect_workspaces_to_sandbox};
+
+    #[test]
+    fn test_next_signer_and_nonce_rotates_keys() {
+        let mut account = Account::new(
+            "user_0.test.near".parse().unwrap(),
+            SecretKey::from_random(near_crypto::KeyType::ED25519),
+            10,
+        );
+        account.additional_keys.push(AccountKey::new(
+            SecretKey::from_random(near_crypto::KeyType::ED25519),
+            20,
+        ));
+
+        let public_keys: Vec<_> = account.public_keys().cloned().collect();
+        let mut used = vec![];
+        for _ in 0..4 {
+            let (signer, nonce) = account.next_signer_and_nonce();
+            used.push((signer.public_key(), nonce));
+        }
+        assert_eq!(
+            used,
+            vec![
+                (public_keys[0].clone(), 11),
+                (public_keys[1].clone(), 21),
+                (public_keys[0].clone(), 12),
+                (public_keys[1].clone(), 22),
+            ]
+        );
+        assert_eq!(account.nonces(), vec![12, 22]);
+
+        // Rotation state is not persisted.
+        let json = serde_json::to_string(&account).unwrap();
+        let account: Account = serde_json::from_str(&json).unwrap();
+        assert_eq!(account.nonces(), vec![12, 22]);
+    }
+
+    #[test]
+    fn test_key_type_selection() {
+        assert_eq!(
+            parse_key_type_selection("secp256k1").unwrap(),
+            KeyTypeSelection::Secp256k1
+        );
+        assert!(parse_key_type_selection("mix:1.5").is_err());
+        assert!(parse_key_type_selection("rsa").is_err());
+        assert!(matches!(
+            parse_key_type("Secp256k1").unwrap(),
+            near_crypto::KeyType::SECP256K1
+        ));
+        assert!(parse_key_type("mix:1").is_err());
+
+        let selection = parse_key_type_selection("mix:0.25").unwrap();
+        let key_types: Vec<_> = (0..8).map(|i| selection.key_type(i)).collect();
+        let num_secp256k1 = key_types
+            .iter()
+            .filter(|key_type| matches!(key_type, near_crypto::KeyType::SECP256K1))
+            .count();
+        assert_eq!(num_secp256k1, 2);
+        assert!(matches!(key_types[3], near_crypto::KeyType::SECP256K1));
+    }
+
+    #[test]
+    fn test_secret_key_from_rng() {
+        let key = |seed, key_type| secret_key_from_rng(key_type, &mut seeded_rng(Some(seed)).0);
+        let ed25519 = key(1, near_crypto::KeyType::ED25519);
+        assert_eq!(ed25519, key(1, near_crypto::KeyType::ED25519));
+        assert_ne!(ed25519, key(2, near_crypto::KeyType::ED25519));
+        let secp256k1 = key(1, near_crypto::KeyType::SECP256K1);
+        assert!(matches!(
+            secp256k1.key_type(),
+            near_crypto::KeyType::SECP256K1
+        ));
+        assert_eq!(secp256k1, key(1, near_crypto::KeyType::SECP256K1));
+    }
+
+    #[test]
+    fn test_function_call_keys_are_rotated_separately() {
+        let receiver_id: AccountId = "contract.test.near".parse().unwrap();
+        let mut account = Account::new(
+            "user_0.test.near".parse().unwrap(),
+            SecretKey::from_random(near_crypto::KeyType::ED25519),
+            10,
+        );
+        account.additional_keys.push(AccountKey::new_function_call(
+            SecretKey::from_random(near_crypto::KeyType::ED25519),
+            20,
+            FunctionCallPermission {
+                allowance: None,
+                receiver_id: receiver_id.to_string(),
+                method_names: vec!["ft_transfer".to_string()],
+            },
+        ));
+
+        assert!(account.has_function_call_key(&receiver_id, "ft_transfer"));
+        assert!(!account.has_function_call_key(&receiver_id, "storage_deposit"));
+
+        // Full access rotation skips the function call key.
+        assert_eq!(account.next_signer_and_nonce().1, 11);
+        assert_eq!(account.next_signer_and_nonce().1, 12);
+
+        let (_, nonce) = account
+            .next_function_call_signer_and_nonce(&receiver_id, "ft_transfer")
+            .unwrap();
+        assert_eq!(nonce, 21);
+        assert!(account
+            .next_function_call_signer_and_nonce(&receiver_id, "storage_deposit")
+            .is_none());
+        assert!(account
+            .next_function_call_signer_and_nonce(&"other.test.near".parse().unwrap(), "ft_transfer")
+            .is_none());
+    }
 
     // TODO make these constants parameters
     const RPC_ADDR: &str = "http://localhost:3030";
@@ -0,0 +1,392 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use log::{info, warn};
+use near_primitives::{
+    serialize::dec_format,
+    shard_layout::{account_id_to_shard_id, ShardLayout},
+    types::{Balance, BlockHeight, Gas, ShardId},
+    views::{BlockView, ChunkView},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle, time};
+
+use crate::{
+    congestion::{ChunkCongestion, CongestionThresholds, ShardCongestionReport},
+    rpc::{get_latest_block, get_protocol_config, scan_blocks_up_to},
+    rpc_client::RpcClient,
+};
+
+/// Chunks using at least this fraction of their gas limit count as full.
+const FULL_CHUNK_GAS_RATIO: f64 = 0.9;
+
+#[derive(Args, Debug)]
+pub struct BlockScanArgs {
+    /// Scan the blocks and chunks produced during the run and report per height how full chunks
+    /// are (gas used and limit, transactions, incoming receipts) and which chunks are missing.
+    #[arg(long)]
+    pub scan_blocks: bool,
+    /// With `--scan-blocks`, the interval (in milliseconds) at which new final blocks are fetched.
+    #[arg(long, default_value_t = 500)]
+    pub block_scan_interval_millis: u64,
+    /// With `--scan-blocks`, keep scanning for up to this many seconds after the run until no
+    /// shard has delayed or buffered receipts, to see how long congestion takes to clear.
+    #[arg(long, default_value_t = 0)]
+    pub block_scan_drain_secs: u64,
+}
+
+/// Contents of the chunk of one shard in a block, as far as they are relevant for throughput.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkStats {
+    pub shard_id: ShardId,
+    /// The block contains no new chunk for the shard. Other fields are zero then.
+    pub missing: bool,
+    /// Gas used as reported in the chunk header.
+    pub gas_used: Gas,
+    pub gas_limit: Gas,
+    pub num_transactions: usize,
+    /// Receipts in the block's chunks that are addressed to this shard.
+    pub num_incoming_receipts: usize,
+    /// Congestion after applying the chunk, if the protocol version has congestion control.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub congestion: Option<ChunkCongestion>,
+}
+
+impl ChunkStats {
+    /// Fraction of the gas limit used by the chunk.
+    pub fn gas_utilization(&self) -> f64 {
+        if self.gas_limit == 0 {
+            0.0
+        } else {
+            self.gas_used as f64 / self.gas_limit as f64
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlockStats {
+    pub height: BlockHeight,
+    /// Milliseconds since the unix epoch.
+    pub timestamp_ms: u64,
+    #[serde(default, with = "dec_format")]
+    pub gas_price: Balance,
+    pub chunks: Vec<ChunkStats>,
+}
+
+impl BlockStats {
+    fn new(
+        block: &BlockView,
+        chunks: &[ChunkView],
+        shard_layout: &ShardLayout,
+        congestion_thresholds: &CongestionThresholds,
+    ) -> Self {
+        let mut num_incoming_receipts: HashMap<ShardId, usize> = HashMap::new();
+        for receipt in chunks.iter().flat_map(|chunk| chunk.receipts.iter()) {
+            let shard_id = account_id_to_shard_id(&receipt.receiver_id, shard_layout);
+            *num_incoming_receipts.entry(shard_id).or_default() += 1;
+        }
+
+        let chunks = block
+            .chunks
+            .iter()
+            .map(|header| {
+                let shard_id = header.shard_id;
+                // Otherwise the chunk is missing and the header refers to a previous chunk.
+                let chunk = chunks
+                    .iter()
+                    .find(|chunk| chunk.header.chunk_hash == header.chunk_hash)
+                    .filter(|_| header.height_included == block.header.height);
+                match chunk {
+                    Some(chunk) => ChunkStats {
+                        shard_id,
+                        missing: false,
+                        gas_used: header.gas_used,
+                        gas_limit: header.gas_limit,
+                        num_transactions: chunk.transactions.len(),
+                        num_incoming_receipts: num_incoming_receipts
+                            .get(&shard_id)
+                            .copied()
+                            .unwrap_or_default(),
+                        congestion: header
+                            .congestion_info
+                            .as_ref()
+                            .map(|info| ChunkCongestion::new(info, congestion_thresholds)),
+                    },
+                    None => ChunkStats {
+                        shard_id,
+                        missing: true,
+                        gas_used: 0,
+                        gas_limit: 0,
+                        num_transactions: 0,
+                        num_incoming_receipts: 0,
+                        congestion: None,
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            height: block.header.height,
+            timestamp_ms: block.header.timestamp_nanosec / 1_000_000,
+            gas_price: block.header.gas_price,
+            chunks,
+        }
+    }
+}
+
+/// Fullness of chunks during a run. If chunks are rarely full while transactions pile up, the
+/// chunk gas limit is not what limits throughput, but e.g. the time a chunk producer spends
+/// adding transactions (`produce_chunk_add_transactions_time_limit`).
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BlockScanReport {
+    pub num_blocks: usize,
+    pub num_chunks: usize,
+    pub num_missing_chunks: usize,
+    /// Mean over chunks that are not missing.
+    pub mean_gas_utilization: f64,
+    /// Chunks using at least 90% of their gas limit.
+    pub num_full_chunks: usize,
+    pub max_transactions_per_chunk: usize,
+    /// Per shard, when receipts queued up and when transactions were rejected.
+    #[serde(default)]
+    pub congestion: Vec<ShardCongestionReport>,
+    pub blocks: Vec<BlockStats>,
+}
+
+impl BlockScanReport {
+    pub fn new(blocks: Vec<BlockStats>, reject_tx_congestion_threshold: f64) -> Self {
+        let chunks = || blocks.iter().flat_map(|block| block.chunks.iter());
+        let present_chunks = || chunks().filter(|chunk| !chunk.missing);
+        let num_present = present_chunks().count();
+        let mean_gas_utilization = if num_present == 0 {
+            0.0
+        } else {
+            present_chunks()
+                .map(ChunkStats::gas_utilization)
+                .sum::<f64>()
+                / num_present as f64
+        };
+        Self {
+            num_blocks: blocks.len(),
+            num_chunks: chunks().count(),
+            num_missing_chunks: chunks().filter(|chunk| chunk.missing).count(),
+            mean_gas_utilization,
+            num_full_chunks: present_chunks()
+                .filter(|chunk| chunk.gas_utilization() >= FULL_CHUNK_GAS_RATIO)
+                .count(),
+            max_transactions_per_chunk: chunks()
+                .map(|chunk| chunk.num_transactions)
+                .max()
+                .unwrap_or_default(),
+            congestion: ShardCongestionReport::from_blocks(&blocks, reject_tx_congestion_threshold),
+            blocks,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Scanned {} blocks: {} chunks, {} missing, {} full; mean gas utilization {:.1}%, up to {} txs per chunk",
+            self.num_blocks,
+            self.num_chunks,
+            self.num_missing_chunks,
+            self.num_full_chunks,
+            self.mean_gas_utilization * 100.0,
+            self.max_transactions_per_chunk
+        );
+        for shard in self.congestion.iter() {
+            shard.log_summary();
+        }
+    }
+}
+
+/// Fetches every final block and its chunks in the background, see [`BlockScanner::spawn`].
+pub struct BlockScanner {
+    rpc_client: RpcClient,
+    shard_layout: ShardLayout,
+    congestion_thresholds: CongestionThresholds,
+    interval: Duration,
+    drain_duration: Duration,
+    last_scanned_height: BlockHeight,
+    blocks: Vec<BlockStats>,
+}
+
+pub struct BlockScanHandle {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<BlockScanReport>,
+}
+
+impl BlockScanHandle {
+    /// Scans blocks up to the latest final one, or until congestion cleared if draining is
+    /// enabled, and returns the report.
+    pub async fn stop(self) -> BlockScanReport {
+        // Sending fails only if the task finished, which it does only after receiving.
+        let _ = self.stop.send(());
+        self.task.await.expect("block scanner should succeed")
+    }
+}
+
+impl BlockScanner {
+    /// Returns `None` unless scanning is enabled. Blocks above the latest final block are
+    /// scanned.
+    pub async fn from_args(
+        rpc_client: RpcClient,
+        args: &BlockScanArgs,
+    ) -> anyhow::Result<Option<Self>> {
+        if !args.scan_blocks {
+            return Ok(None);
+        }
+        let protocol_config = get_protocol_config(&rpc_client).await?;
+        let congestion_config = &protocol_config.runtime_config.congestion_control_config;
+        let congestion_thresholds = CongestionThresholds {
+            max_incoming_gas: congestion_config.max_congestion_incoming_gas,
+            max_outgoing_gas: congestion_config.max_congestion_outgoing_gas,
+            max_memory_consumption: congestion_config.max_congestion_memory_consumption,
+            reject_tx_threshold: congestion_config.reject_tx_congestion_threshold,
+        };
+        let start_height = get_latest_block(&rpc_client).await?.header.height;
+        Ok(Some(Self {
+            rpc_client,
+            shard_layout: protocol_config.shard_layout.clone(),
+            congestion_thresholds,
+            interval: Duration::from_millis(args.block_scan_interval_millis),
+            drain_duration: Duration::from_secs(args.block_scan_drain_secs),
+            last_scanned_height: start_height,
+            blocks: vec![],
+        }))
+    }
+
+    pub fn spawn(self) -> BlockScanHandle {
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(self.run(stop_rx));
+        BlockScanHandle { stop, task }
+    }
+
+    async fn run(mut self, mut stop: oneshot::Receiver<()>) -> BlockScanReport {
+        let mut interval = time::interval(self.interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            let stopped = tokio::select! {
+                _ = &mut stop => true,
+                _ = interval.tick() => false,
+            };
+            if let Err(err) = self.scan_new_blocks().await {
+                // The next scan retries heights that were not scanned.
+                warn!("failed to scan blocks: {err}");
+            }
+            if stopped {
+                break;
+            }
+        }
+        self.drain(interval).await;
+        BlockScanReport::new(self.blocks, self.congestion_thresholds.reject_tx_threshold)
+    }
+
+    /// Keeps scanning until the latest scanned block has no queued receipts or the drain duration
+    /// passed.
+    async fn drain(&mut self, mut interval: time::Interval) {
+        if self.drain_duration.is_zero() {
+            return;
+        }
+        let started = Instant::now();
+        while !self.is_drained() {
+            if started.elapsed() >= self.drain_duration {
+                warn!(
+                    "receipts are still queued {:.0}s after the run",
+                    started.elapsed().as_secs_f64()
+                );
+                return;
+            }
+            interval.tick().await;
+            if let Err(err) = self.scan_new_blocks().await {
+                warn!("failed to scan blocks: {err}");
+            }
+        }
+        info!(
+            "No receipts queued {:.1}s after the run",
+            started.elapsed().as_secs_f64()
+        );
+    }
+
+    fn is_drained(&self) -> bool {
+        self.blocks.last().is_some_and(|block| {
+            !block.chunks.iter().any(|chunk| {
+                chunk
+                    .congestion
+                    .as_ref()
+                    .is_some_and(ChunkCongestion::has_queued_receipts)
+            })
+        })
+    }
+
+    async fn scan_new_blocks(&mut self) -> anyhow::Result<()> {
+        let latest = get_latest_block(&self.rpc_client).await?;
+        let Self {
+            rpc_client,
+            shard_layout,
+            congestion_thresholds,
+            last_scanned_height,
+            blocks,
+            ..
+        } = self;
+        // Heights that were not scanned are retried by the next scan.
+        scan_blocks_up_to(rpc_client, last_scanned_height, latest, |block, chunks| {
+            blocks.push(BlockStats::new(
+                &block,
+                &chunks,
+                shard_layout,
+                congestion_thresholds,
+            ));
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(
+        shard_id: ShardId,
+        missing: bool,
+        gas_used: Gas,
+        num_transactions: usize,
+    ) -> ChunkStats {
+        ChunkStats {
+            shard_id,
+            missing,
+            gas_used,
+            gas_limit: if missing { 0 } else { 1000 },
+            num_transactions,
+            num_incoming_receipts: 0,
+            congestion: None,
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let blocks = vec![
+            BlockStats {
+                height: 10,
+                timestamp_ms: 0,
+                gas_price: 0,
+                chunks: vec![chunk(0, false, 950, 30), chunk(1, false, 250, 5)],
+            },
+            BlockStats {
+                height: 11,
+                timestamp_ms: 1000,
+                gas_price: 0,
+                chunks: vec![chunk(0, false, 500, 12), chunk(1, true, 0, 0)],
+            },
+        ];
+        let report = BlockScanReport::new(blocks, 1.0);
+        assert_eq!(report.num_blocks, 2);
+        assert_eq!(report.num_chunks, 4);
+        assert_eq!(report.num_missing_chunks, 1);
+        assert_eq!(report.num_full_chunks, 1);
+        assert_eq!(report.max_transactions_per_chunk, 30);
+        assert!((report.mean_gas_utilization - 0.5667).abs() < 1e-3);
+    }
+}
@@ -3,21 +3,22 @@ use std::{
     time::Duration,
 };
 
-use near_jsonrpc_client::JsonRpcClient;
 use near_primitives::{
     hash::CryptoHash,
-    types::{BlockReference, Finality},
+    types::{BlockHeight, BlockReference, Finality},
     views::BlockView,
 };
-use tokio::time;
+use tokio::{sync::broadcast, time};
 
-use crate::rpc::get_block;
+use crate::{rpc::get_block, rpc_client::RpcClient};
 
 pub struct BlockService {
-    rpc_client: JsonRpcClient,
+    rpc_client: RpcClient,
     refresh_interval: Duration,
     /// A block that's refreshed every `refresh_interval`.
     block: RwLock<BlockView>,
+    /// Publishes refreshed blocks that are higher than the previous one.
+    new_blocks: broadcast::Sender<BlockView>,
 }
 
 impl BlockService {
@@ -26,18 +27,29 @@ impl BlockService {
     /// # Panics
     ///
     /// Panics if getting a new block fails.
-    pub async fn new(rpc_client: JsonRpcClient) -> Self {
+    pub async fn new(rpc_client: RpcClient) -> Self {
         // Getting a new block hash is relatively cheap, hence just do it every 30 seconds even
         // if longer refresh intervals might be fine too. A shorter interval reduces the chances
         // expiring transactions.
-        let refresh_interval = Duration::from_secs(30);
+        Self::with_refresh_interval(rpc_client, Duration::from_secs(30)).await
+    }
+
+    /// Like [`Self::new`], with a custom interval. Subscribers to new blocks might want an interval
+    /// shorter than the block time to see blocks soon after they become final.
+    ///
+    /// # Panics
+    ///
+    /// Panics if getting a new block fails.
+    pub async fn with_refresh_interval(rpc_client: RpcClient, refresh_interval: Duration) -> Self {
         let block = get_block(&rpc_client, BlockReference::Finality(Finality::Final))
             .await
             .expect("should be able to get a block");
+        let (new_blocks, _) = broadcast::channel(1024);
         Self {
             rpc_client,
             refresh_interval,
             block: RwLock::new(block),
+            new_blocks,
         }
     }
 
@@ -57,8 +69,12 @@ impl BlockService {
                     get_block(&self.rpc_client, BlockReference::Finality(Finality::Final))
                         .await
                         .expect("should be able to get a block");
-                let mut block = self.block.write().unwrap();
-                *block = new_block;
+                let is_new = new_block.header.height > self.get_block_height();
+                *self.block.write().unwrap() = new_block.clone();
+                if is_new {
+                    // Sending fails only if there are no subscribers, which is fine.
+                    let _ = self.new_blocks.send(new_block);
+                }
             }
         });
     }
@@ -68,6 +84,16 @@ impl BlockService {
     }
 
     pub fn get_block_hash(&self) -> CryptoHash {
-        self.get_block().header.hash
+        self.block.read().unwrap().header.hash
+    }
+
+    pub fn get_block_height(&self) -> BlockHeight {
+        self.block.read().unwrap().header.height
+    }
+
+    /// Receives blocks fetched after subscribing. Heights may be skipped if blocks are produced
+    /// faster than `refresh_interval`.
+    pub fn subscribe(&self) -> broadcast::Receiver<BlockView> {
+        self.new_blocks.subscribe()
     }
 }
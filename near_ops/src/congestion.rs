@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use log::{info, warn};
+use near_primitives::{
+    types::{BlockHeight, Gas, ShardId},
+    views::CongestionInfoView,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::block_scan::BlockStats;
+
+/// The parts of the congestion control config needed to derive congestion levels from the
+/// congestion info in chunk headers.
+#[derive(Clone, Debug)]
+pub struct CongestionThresholds {
+    /// Gas of delayed receipts at which a shard is fully congested.
+    pub max_incoming_gas: Gas,
+    /// Gas of buffered outgoing receipts at which a shard is fully congested.
+    pub max_outgoing_gas: Gas,
+    /// Size of delayed and buffered receipts at which a shard is fully congested.
+    pub max_memory_consumption: u64,
+    /// From this congestion level on, transactions to the shard are rejected.
+    pub reject_tx_threshold: f64,
+}
+
+/// Congestion of a shard after applying one of its chunks, as reported in the chunk header.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkCongestion {
+    /// Gas attached to receipts in the delayed receipts queue.
+    pub delayed_receipts_gas: u128,
+    /// Gas attached to receipts buffered for forwarding to other shards.
+    pub buffered_receipts_gas: u128,
+    /// Size of delayed and buffered receipts.
+    pub receipt_bytes: u64,
+    /// The shard allowed to forward receipts to this shard when it is fully congested.
+    pub allowed_shard: u16,
+    /// Between 0 (not congested) and 1 (fully congested), computed like nearcore does except
+    /// that missed chunks are not taken into account.
+    pub level: f64,
+}
+
+impl ChunkCongestion {
+    pub fn new(info: &CongestionInfoView, thresholds: &CongestionThresholds) -> Self {
+        let ratio = |value: f64, max: f64| if max > 0.0 { value / max } else { 0.0 };
+        let incoming = ratio(
+            info.delayed_receipts_gas as f64,
+            thresholds.max_incoming_gas as f64,
+        );
+        let outgoing = ratio(
+            info.buffered_receipts_gas as f64,
+            thresholds.max_outgoing_gas as f64,
+        );
+        let memory = ratio(
+            info.receipt_bytes as f64,
+            thresholds.max_memory_consumption as f64,
+        );
+        Self {
+            delayed_receipts_gas: info.delayed_receipts_gas,
+            buffered_receipts_gas: info.buffered_receipts_gas,
+            receipt_bytes: info.receipt_bytes,
+            allowed_shard: info.allowed_shard,
+            level: incoming.max(outgoing).max(memory).min(1.0),
+        }
+    }
+
+    /// Whether receipts are queued, i.e. the shard has not caught up with its load.
+    pub fn has_queued_receipts(&self) -> bool {
+        self.delayed_receipts_gas > 0 || self.buffered_receipts_gas > 0
+    }
+}
+
+/// When and how badly a shard was congested during a run.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ShardCongestionReport {
+    pub shard_id: ShardId,
+    pub max_level: f64,
+    pub max_delayed_receipts_gas: u128,
+    pub max_buffered_receipts_gas: u128,
+    pub max_receipt_bytes: u64,
+    /// Chunks after which receipts were delayed or buffered.
+    pub num_chunks_with_queued_receipts: usize,
+    /// Chunks after which transactions to the shard were rejected.
+    pub num_rejecting_chunks: usize,
+    pub first_queued_height: Option<BlockHeight>,
+    pub last_queued_height: Option<BlockHeight>,
+    pub first_rejecting_height: Option<BlockHeight>,
+    pub last_rejecting_height: Option<BlockHeight>,
+}
+
+impl ShardCongestionReport {
+    /// Summarizes the congestion of every shard in `blocks`, ordered by shard id.
+    pub fn from_blocks(blocks: &[BlockStats], reject_tx_threshold: f64) -> Vec<Self> {
+        let mut reports: BTreeMap<ShardId, Self> = BTreeMap::new();
+        for block in blocks.iter() {
+            for chunk in block.chunks.iter() {
+                let Some(congestion) = &chunk.congestion else {
+                    continue;
+                };
+                let report = reports.entry(chunk.shard_id).or_insert_with(|| Self {
+                    shard_id: chunk.shard_id,
+                    ..Default::default()
+                });
+                report.max_level = report.max_level.max(congestion.level);
+                report.max_delayed_receipts_gas = report
+                    .max_delayed_receipts_gas
+                    .max(congestion.delayed_receipts_gas);
+                report.max_buffered_receipts_gas = report
+                    .max_buffered_receipts_gas
+                    .max(congestion.buffered_receipts_gas);
+                report.max_receipt_bytes = report.max_receipt_bytes.max(congestion.receipt_bytes);
+                if congestion.has_queued_receipts() {
+                    report.num_chunks_with_queued_receipts += 1;
+                    report.first_queued_height.get_or_insert(block.height);
+                    report.last_queued_height = Some(block.height);
+                }
+                if congestion.level >= reject_tx_threshold {
+                    report.num_rejecting_chunks += 1;
+                    report.first_rejecting_height.get_or_insert(block.height);
+                    report.last_rejecting_height = Some(block.height);
+                }
+            }
+        }
+        reports.into_values().collect()
+    }
+
+    pub fn log_summary(&self) {
+        let (Some(first), Some(last)) = (self.first_queued_height, self.last_queued_height) else {
+            info!("Shard {} was not congested", self.shard_id);
+            return;
+        };
+        info!(
+            "Shard {} queued receipts after {} chunks between heights {first} and {last}; max congestion level {:.2}, max delayed receipts gas {}, max buffered receipts gas {}",
+            self.shard_id,
+            self.num_chunks_with_queued_receipts,
+            self.max_level,
+            self.max_delayed_receipts_gas,
+            self.max_buffered_receipts_gas,
+        );
+        if let (Some(first), Some(last)) = (self.first_rejecting_height, self.last_rejecting_height)
+        {
+            warn!(
+                "Shard {} rejected transactions after {} chunks between heights {first} and {last}",
+                self.shard_id, self.num_rejecting_chunks
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_scan::ChunkStats;
+
+    fn thresholds() -> CongestionThresholds {
+        CongestionThresholds {
+            max_incoming_gas: 1000,
+            max_outgoing_gas: 100,
+            max_memory_consumption: 10_000,
+            reject_tx_threshold: 0.5,
+        }
+    }
+
+    fn block(height: BlockHeight, delayed_receipts_gas: u128) -> BlockStats {
+        let info = CongestionInfoView {
+            delayed_receipts_gas,
+            buffered_receipts_gas: 0,
+            receipt_bytes: 0,
+            allowed_shard: 0,
+        };
+        BlockStats {
+            height,
+            timestamp_ms: 0,
+            gas_price: 0,
+            chunks: vec![ChunkStats {
+                shard_id: 3,
+                missing: false,
+                gas_used: 0,
+                gas_limit: 0,
+                num_transactions: 0,
+                num_incoming_receipts: 0,
+                congestion: Some(ChunkCongestion::new(&info, &thresholds())),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_congestion_level() {
+        let info = CongestionInfoView {
+            delayed_receipts_gas: 200,
+            buffered_receipts_gas: 50,
+            receipt_bytes: 20_000,
+            allowed_shard: 1,
+        };
+        let congestion = ChunkCongestion::new(&info, &thresholds());
+        // Memory consumption exceeds its maximum.
+        assert_eq!(congestion.level, 1.0);
+
+        let info = CongestionInfoView {
+            receipt_bytes: 0,
+            ..info
+        };
+        assert_eq!(ChunkCongestion::new(&info, &thresholds()).level, 0.5);
+    }
+
+    #[test]
+    fn test_shard_report() {
+        let blocks = [
+            block(10, 0),
+            block(11, 300),
+            block(12, 700),
+            block(13, 100),
+            block(14, 0),
+        ];
+        let reports = ShardCongestionReport::from_blocks(&blocks, 0.5);
+        assert_eq!(
+            reports,
+            vec![ShardCongestionReport {
+                shard_id: 3,
+                max_level: 0.7,
+                max_delayed_receipts_gas: 700,
+                max_buffered_receipts_gas: 0,
+                max_receipt_bytes: 0,
+                num_chunks_with_queued_receipts: 3,
+                num_rejecting_chunks: 1,
+                first_queued_height: Some(11),
+                last_queued_height: Some(13),
+                first_rejecting_height: Some(12),
+                last_rejecting_height: Some(12),
+            }]
+        );
+    }
+}
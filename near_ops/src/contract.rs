@@ -1,8 +1,94 @@
 use std::{fs::File, io::Read, path::PathBuf};
 
+use near_primitives::types::AccountId;
+use rand::Rng;
+
 pub fn read_wasm_bytes(path: &PathBuf) -> anyhow::Result<Vec<u8>> {
     let mut file = File::open(path)?;
     let mut wasm = Vec::new();
     file.read_to_end(&mut wasm)?;
     Ok(wasm)
 }
+
+/// Function call arguments with placeholders that are substituted per transaction:
+///
+/// - `{sender}`: id of the account sending the transaction
+/// - `{receiver}`: id of the called contract
+/// - `{random_u64}`: a random `u64`
+/// - `{i}`: index of the transaction
+///
+/// # Example
+///
+/// `{"receiver_id": "{receiver}", "amount": "{random_u64}"}`
+#[derive(Clone, Debug)]
+pub struct ArgsTemplate {
+    template: String,
+}
+
+impl ArgsTemplate {
+    /// Fails if substituting placeholders does not result in a json object.
+    pub fn new(template: String) -> anyhow::Result<Self> {
+        let args_template = Self { template };
+        let sample = args_template.render(
+            &"sender.near".parse().unwrap(),
+            &"receiver.near".parse().unwrap(),
+            0,
+            &mut rand::thread_rng(),
+        );
+        let sample: serde_json::Value = serde_json::from_str(&sample)
+            .map_err(|err| anyhow::anyhow!("args template is not valid json: {err}"))?;
+        anyhow::ensure!(sample.is_object(), "args template must be a json object");
+        Ok(args_template)
+    }
+
+    pub fn render(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        i: u64,
+        rng: &mut impl Rng,
+    ) -> String {
+        let mut args = self
+            .template
+            .replace("{sender}", sender_id.as_str())
+            .replace("{receiver}", receiver_id.as_str())
+            .replace("{i}", &i.to_string());
+        // Each occurrence gets its own random value.
+        while let Some(pos) = args.find("{random_u64}") {
+            args.replace_range(
+                pos..pos + "{random_u64}".len(),
+                &rng.gen::<u64>().to_string(),
+            );
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_template() {
+        let template = ArgsTemplate::new(
+            r#"{"from": "{sender}", "to": "{receiver}", "memo": "tx {i}", "a": {random_u64}, "b": {random_u64}}"#
+                .to_string(),
+        )
+        .unwrap();
+        let args = template.render(
+            &"alice.near".parse().unwrap(),
+            &"ft.near".parse().unwrap(),
+            7,
+            &mut rand::thread_rng(),
+        );
+        let args: serde_json::Value = serde_json::from_str(&args).unwrap();
+        assert_eq!(args["from"], "alice.near");
+        assert_eq!(args["to"], "ft.near");
+        assert_eq!(args["memo"], "tx 7");
+        assert!(args["a"].is_u64());
+        assert!(args["b"].is_u64());
+
+        assert!(ArgsTemplate::new("[{i}]".to_string()).is_err());
+        assert!(ArgsTemplate::new(r#"{"a": {sender}}"#.to_string()).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write as _},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use near_primitives::{hash::CryptoHash, types::BlockHeight};
+use tokio::{sync::oneshot, task::JoinHandle, time};
+
+use crate::{
+    block_service::BlockService,
+    load_metrics::{load_metrics, LoadMetricsSnapshot, ResponseOutcome},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Switches to the alternate screen of the terminal and hides the cursor.
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// A live dashboard of the benchmark in the terminal, based on [`load_metrics`].
+pub struct Dashboard {
+    block_service: Arc<BlockService>,
+    /// Transactions per second the benchmark tries to send.
+    target_rate: f64,
+}
+
+/// A dashboard refreshing in the background, see [`Dashboard::spawn`].
+pub struct DashboardHandle {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<String>,
+    /// Restores the terminal also if the handle is dropped without calling `stop`, e.g. when the
+    /// benchmark returns early with an error.
+    screen: ScreenGuard,
+}
+
+impl DashboardHandle {
+    /// Restores the terminal and prints the dashboard one last time.
+    pub async fn stop(self) {
+        let Self { stop, task, screen } = self;
+        // Sending fails only if the task finished, which it does only after receiving.
+        let _ = stop.send(());
+        let final_frame = task.await.expect("dashboard should succeed");
+        drop(screen);
+        print!("{final_frame}");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Keeps the terminal on the alternate screen until dropped.
+struct ScreenGuard;
+
+impl ScreenGuard {
+    fn enter() -> Self {
+        print!("{ENTER_SCREEN}");
+        let _ = io::stdout().flush();
+        Self
+    }
+}
+
+impl Drop for ScreenGuard {
+    fn drop(&mut self) {
+        print!("{LEAVE_SCREEN}");
+        let _ = io::stdout().flush();
+    }
+}
+
+impl Dashboard {
+    pub fn new(block_service: Arc<BlockService>, interval_duration: Duration) -> Self {
+        Self {
+            block_service,
+            target_rate: 1.0 / interval_duration.as_secs_f64(),
+        }
+    }
+
+    pub fn spawn(self) -> DashboardHandle {
+        let (stop, stop_rx) = oneshot::channel();
+        let screen = ScreenGuard::enter();
+        let task = tokio::spawn(self.run(stop_rx));
+        DashboardHandle { stop, task, screen }
+    }
+
+    /// Refreshes the dashboard until stopped and returns the last frame.
+    async fn run(self, mut stop: oneshot::Receiver<()>) -> String {
+        let started = Instant::now();
+        let mut previous = load_metrics().snapshot();
+
+        let mut interval = time::interval(REFRESH_INTERVAL);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        // The first tick completes immediately, when there is nothing to show yet.
+        interval.tick().await;
+        let mut last_tick = Instant::now();
+        loop {
+            let stopped = tokio::select! {
+                _ = &mut stop => true,
+                _ = interval.tick() => false,
+            };
+            let current = load_metrics().snapshot();
+            let frame = render_frame(&FrameInput {
+                elapsed: started.elapsed(),
+                window: last_tick.elapsed(),
+                previous: &previous,
+                current: &current,
+                target_rate: self.target_rate,
+                block_height: self.block_service.get_block_height(),
+                block_hash: self.block_service.get_block_hash(),
+            });
+            previous = current;
+            last_tick = Instant::now();
+            if stopped {
+                return frame;
+            }
+            print!("{CLEAR_SCREEN}{frame}");
+            // A failed flush only delays the frame.
+            let _ = io::stdout().flush();
+        }
+    }
+}
+
+struct FrameInput<'a> {
+    elapsed: Duration,
+    /// Time between `previous` and `current`.
+    window: Duration,
+    previous: &'a LoadMetricsSnapshot,
+    current: &'a LoadMetricsSnapshot,
+    target_rate: f64,
+    block_height: BlockHeight,
+    block_hash: CryptoHash,
+}
+
+fn render_frame(input: &FrameInput) -> String {
+    let FrameInput {
+        elapsed,
+        window,
+        previous,
+        current,
+        target_rate,
+        block_height,
+        block_hash,
+    } = input;
+    let window_secs = window.as_secs_f64().max(f64::EPSILON);
+    let rate = |current: u64, previous: u64| (current - previous) as f64 / window_secs;
+
+    let mut out = String::new();
+    writeln!(out, "Benchmark running for {:.0}s", elapsed.as_secs_f64()).unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "Send rate:       {:>10.1} tx/s (target {:.1} tx/s)",
+        rate(current.txs_sent, previous.txs_sent),
+        target_rate
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "Responses:       {:>10.1} /s",
+        rate(current.num_all_responses(), previous.num_all_responses())
+    )
+    .unwrap();
+    writeln!(out, "In flight:       {:>10}", current.outstanding_requests).unwrap();
+    writeln!(
+        out,
+        "Sent / received: {:>10} / {}",
+        current.txs_sent,
+        current.num_all_responses()
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(
+        out,
+        "Errors:          {:>10} ({:.1} /s)",
+        current.num_errors(),
+        rate(current.num_errors(), previous.num_errors())
+    )
+    .unwrap();
+    for outcome in [
+        ResponseOutcome::Failed,
+        ResponseOutcome::NonceError,
+        ResponseOutcome::Timeout,
+        ResponseOutcome::OtherRpcError,
+    ] {
+        writeln!(
+            out,
+            "  {:<15}{:>10}",
+            outcome.label(),
+            current.num_responses(outcome)
+        )
+        .unwrap();
+    }
+    writeln!(out).unwrap();
+
+    let quantiles = [0.5, 0.9, 0.99]
+        .map(|quantile| {
+            let latency = match current.latency_quantile_since(previous, quantile) {
+                None => "-".to_string(),
+                Some(upper_bound) if upper_bound.is_infinite() => "> 60s".to_string(),
+                Some(upper_bound) => format!("<= {upper_bound}s"),
+            };
+            format!("p{}: {latency}", quantile * 100.0)
+        })
+        .join(", ");
+    writeln!(out, "Latency:         {quantiles}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "Latest block:    #{block_height} {block_hash}").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_frame() {
+        let frame = render_frame(&FrameInput {
+            elapsed: Duration::from_secs(10),
+            window: Duration::from_secs(1),
+            previous: &LoadMetricsSnapshot::default(),
+            current: &LoadMetricsSnapshot {
+                txs_sent: 100,
+                outstanding_requests: 20,
+                ..Default::default()
+            },
+            target_rate: 200.0,
+            block_height: 42,
+            block_hash: CryptoHash::default(),
+        });
+        assert!(frame.contains("Send rate:            100.0 tx/s (target 200.0 tx/s)"));
+        assert!(frame.contains("In flight:               20"));
+        assert!(frame.contains("p50: -"));
+        assert!(frame.contains("Latest block:    #42"));
+    }
+}
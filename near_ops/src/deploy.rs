@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    finality::FinalityTimings, latency::LatencySummary, rpc::is_transaction_and_receipts_success,
+    rpc_response_handler::RpcCallResult,
+};
+
+/// The fungible token contract in `assets`, deployed if no wasm is provided.
+pub const BUNDLED_WASM: &[u8] = include_bytes!("../../assets/fungible_token.wasm");
+
+/// Name of the custom section added by [`with_unique_custom_section`].
+const CUSTOM_SECTION_NAME: &str = "near_benchmarking";
+
+/// Appends a custom section containing `run_id` and `id` to a wasm module. Custom sections do not
+/// affect execution, but the code hash differs for each pair of ids. Hence the node cannot reuse a
+/// contract it compiled before, neither in this run nor in previous ones.
+pub fn with_unique_custom_section(wasm: &[u8], run_id: u64, id: u64) -> Vec<u8> {
+    let mut content = leb128_u32(u32::try_from(CUSTOM_SECTION_NAME.len()).unwrap());
+    content.extend_from_slice(CUSTOM_SECTION_NAME.as_bytes());
+    content.extend_from_slice(&run_id.to_le_bytes());
+    content.extend_from_slice(&id.to_le_bytes());
+
+    let mut unique = wasm.to_vec();
+    // The id of custom sections.
+    unique.push(0);
+    unique.extend(leb128_u32(u32::try_from(content.len()).unwrap()));
+    unique.extend(content);
+    unique
+}
+
+fn leb128_u32(mut value: u32) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Response to a transaction deploying a contract to its sender.
+pub struct DeployResult {
+    /// Size of the deployed code in bytes.
+    pub code_size: usize,
+    pub result: RpcCallResult,
+    pub finality_timings: FinalityTimings,
+}
+
+/// Deployments of code of one size. Latencies are those of successful deployments only.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct CodeSizeReport {
+    pub num_sent: u64,
+    pub num_succeeded: u64,
+    pub inclusion_latency: LatencySummary,
+    /// Time from inclusion until the deploy action is executed, which includes compiling the
+    /// contract.
+    pub execution_latency: LatencySummary,
+}
+
+/// Results of a contract deployment benchmark.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeployReport {
+    pub num_sent: u64,
+    pub num_succeeded: u64,
+    /// Whether every deployment had distinct code, see [`with_unique_custom_section`].
+    pub unique_code: bool,
+    /// Keyed by code size in bytes.
+    pub per_code_size: BTreeMap<usize, CodeSizeReport>,
+}
+
+impl DeployReport {
+    pub fn new(results: &[DeployResult], unique_code: bool) -> Self {
+        let mut per_code_size: BTreeMap<usize, CodeSizeReport> = BTreeMap::new();
+        let mut inclusion_latencies: BTreeMap<usize, Vec<Duration>> = BTreeMap::new();
+        let mut execution_latencies: BTreeMap<usize, Vec<Duration>> = BTreeMap::new();
+        for result in results {
+            let report = per_code_size.entry(result.code_size).or_default();
+            report.num_sent += 1;
+            // A failed deployment skips compilation, which would skew the latencies.
+            if !result
+                .result
+                .as_ref()
+                .is_ok_and(is_transaction_and_receipts_success)
+            {
+                continue;
+            }
+            report.num_succeeded += 1;
+
+            let timings = &result.finality_timings;
+            if let Some(included) = timings.included {
+                inclusion_latencies
+                    .entry(result.code_size)
+                    .or_default()
+                    .push(included);
+            }
+            if let (Some(included), Some(executed)) =
+                (timings.included, timings.executed_optimistic)
+            {
+                execution_latencies
+                    .entry(result.code_size)
+                    .or_default()
+                    .push(executed.saturating_sub(included));
+            }
+        }
+        for (code_size, report) in per_code_size.iter_mut() {
+            report.inclusion_latency = LatencySummary::from_durations(
+                inclusion_latencies
+                    .get(code_size)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default(),
+            );
+            report.execution_latency = LatencySummary::from_durations(
+                execution_latencies
+                    .get(code_size)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default(),
+            );
+        }
+
+        Self {
+            num_sent: per_code_size.values().map(|report| report.num_sent).sum(),
+            num_succeeded: per_code_size
+                .values()
+                .map(|report| report.num_succeeded)
+                .sum(),
+            unique_code,
+            per_code_size,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Deployments: {} sent, {} succeeded",
+            self.num_sent, self.num_succeeded
+        );
+        for (code_size, report) in self.per_code_size.iter() {
+            info!(
+                "{code_size} bytes: {} sent, {} succeeded; inclusion latency {}; execution latency {}",
+                report.num_sent,
+                report.num_succeeded,
+                report.inclusion_latency,
+                report.execution_latency
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{InMemorySigner, KeyType, Signer};
+    use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+    use near_jsonrpc_primitives::types::transactions::{
+        RpcTransactionError, RpcTransactionResponse,
+    };
+    use near_primitives::{
+        hash::CryptoHash,
+        transaction::SignedTransaction,
+        types::AccountId,
+        views::{
+            ExecutionMetadataView, ExecutionOutcomeView, ExecutionOutcomeWithIdView,
+            ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum,
+            FinalExecutionStatus, TxExecutionStatus,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_with_unique_custom_section() {
+        let wasm = b"\0asm\x01\0\0\0";
+        let unique = with_unique_custom_section(wasm, 7, 1);
+        assert_eq!(&unique[..wasm.len()], wasm);
+        let section = &unique[wasm.len()..];
+        // Section id, content size, name size, name, id.
+        assert_eq!(section[0], 0);
+        assert_eq!(usize::from(section[1]), 1 + CUSTOM_SECTION_NAME.len() + 16);
+        assert_eq!(usize::from(section[2]), CUSTOM_SECTION_NAME.len());
+        assert_eq!(
+            &section[3..3 + CUSTOM_SECTION_NAME.len()],
+            b"near_benchmarking"
+        );
+        let ids = &section[3 + CUSTOM_SECTION_NAME.len()..];
+        assert_eq!(&ids[..8], &7u64.to_le_bytes());
+        assert_eq!(&ids[8..], &1u64.to_le_bytes());
+        assert_ne!(unique, with_unique_custom_section(wasm, 7, 2));
+        assert_ne!(unique, with_unique_custom_section(wasm, 8, 1));
+
+        assert_eq!(leb128_u32(127), vec![0x7f]);
+        assert_eq!(leb128_u32(624_485), vec![0xe5, 0x8e, 0x26]);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = DeployReport::new(
+            &[
+                succeeded(100, 1000),
+                succeeded(100, 3000),
+                failed(100, 5000),
+                failed(200, 10),
+            ],
+            true,
+        );
+        assert_eq!(report.num_sent, 4);
+        assert_eq!(report.num_succeeded, 2);
+        let small = &report.per_code_size[&100];
+        assert_eq!(small.num_sent, 3);
+        assert_eq!(small.num_succeeded, 2);
+        // The failed deployment is not part of the latencies.
+        assert_eq!(small.inclusion_latency.max, Duration::from_millis(3000));
+        assert_eq!(small.execution_latency.mean, Duration::from_millis(500));
+        let large = &report.per_code_size[&200];
+        assert_eq!(large.num_sent, 1);
+        assert_eq!(large.num_succeeded, 0);
+        assert_eq!(large.inclusion_latency, LatencySummary::default());
+    }
+
+    fn finality_timings(included_ms: u64) -> FinalityTimings {
+        FinalityTimings {
+            tx_hash: CryptoHash::default(),
+            included: Some(Duration::from_millis(included_ms)),
+            executed_optimistic: Some(Duration::from_millis(included_ms + 500)),
+            final_: None,
+        }
+    }
+
+    fn failed(code_size: usize, included_ms: u64) -> DeployResult {
+        DeployResult {
+            code_size,
+            result: Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::TimeoutError,
+            ))),
+            finality_timings: finality_timings(included_ms),
+        }
+    }
+
+    fn succeeded(code_size: usize, included_ms: u64) -> DeployResult {
+        let account_id: AccountId = "user_0.test.near".parse().unwrap();
+        let signer = Signer::from(InMemorySigner::from_random(
+            account_id.clone(),
+            KeyType::ED25519,
+        ));
+        let transaction = SignedTransaction::from_actions(
+            1,
+            account_id.clone(),
+            account_id.clone(),
+            &signer,
+            vec![],
+            CryptoHash::default(),
+            0,
+        );
+        let outcome = ExecutionOutcomeWithIdView {
+            proof: vec![],
+            block_hash: CryptoHash::default(),
+            id: CryptoHash::default(),
+            outcome: ExecutionOutcomeView {
+                logs: vec![],
+                receipt_ids: vec![],
+                gas_burnt: 0,
+                tokens_burnt: 0,
+                executor_id: account_id,
+                status: ExecutionStatusView::SuccessValue(vec![]),
+                metadata: ExecutionMetadataView::default(),
+            },
+        };
+        DeployResult {
+            code_size,
+            result: Ok(RpcTransactionResponse {
+                final_execution_outcome: Some(
+                    FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(
+                        FinalExecutionOutcomeView {
+                            status: FinalExecutionStatus::SuccessValue(vec![]),
+                            transaction: transaction.into(),
+                            transaction_outcome: outcome.clone(),
+                            receipts_outcome: vec![outcome],
+                        },
+                    ),
+                ),
+                final_execution_status: TxExecutionStatus::ExecutedOptimistic,
+            }),
+            finality_timings: finality_timings(included_ms),
+        }
+    }
+}
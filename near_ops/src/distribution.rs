@@ -0,0 +1,250 @@
+use std::fmt;
+
+use clap::Args;
+use rand::Rng;
+
+#[derive(Args, Debug)]
+pub struct AccountDistributionArgs {
+    /// How senders are picked: `round-robin`, `uniform`, `zipf:<exponent>`,
+    /// `hotspot:<hot_fraction>:<hot_share>` or `single-hot`. Hot accounts are the first ones when
+    /// sorted by id.
+    #[arg(long, value_parser = parse_account_distribution, default_value = "round-robin")]
+    pub sender_distribution: AccountDistribution,
+    /// How receivers are picked, see `--sender-distribution`.
+    #[arg(long, value_parser = parse_account_distribution, default_value = "uniform")]
+    pub receiver_distribution: AccountDistribution,
+}
+
+/// How senders or receivers are picked from a set of accounts, parsed by
+/// [`parse_account_distribution`]. Accounts are referred to by their index, so hot accounts are
+/// the first ones returned by [`accounts_from_dir`](crate::account::accounts_from_dir).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AccountDistribution {
+    RoundRobin,
+    Uniform,
+    /// The `k`-th account is picked with probability proportional to `1 / k^exponent`.
+    Zipf {
+        exponent: f64,
+    },
+    /// A fraction `hot_fraction` of accounts receives a share `hot_share` of picks.
+    Hotspot {
+        hot_fraction: f64,
+        hot_share: f64,
+    },
+    /// Always picks the first account.
+    SingleHot,
+}
+
+impl fmt::Display for AccountDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RoundRobin => write!(f, "round-robin"),
+            Self::Uniform => write!(f, "uniform"),
+            Self::Zipf { exponent } => write!(f, "zipf:{exponent}"),
+            Self::Hotspot {
+                hot_fraction,
+                hot_share,
+            } => write!(f, "hotspot:{hot_fraction}:{hot_share}"),
+            Self::SingleHot => write!(f, "single-hot"),
+        }
+    }
+}
+
+/// Parses an [`AccountDistribution`] from one of:
+///
+/// - `round-robin`
+/// - `uniform`
+/// - `zipf:<exponent>`, e.g. `zipf:1.1`
+/// - `hotspot:<hot_fraction>:<hot_share>`, e.g. `hotspot:0.01:0.9` to send 90% of picks to 1% of
+///   accounts
+/// - `single-hot`
+pub fn parse_account_distribution(s: &str) -> anyhow::Result<AccountDistribution> {
+    let mut parts = s.split(':');
+    let name = parts.next().unwrap_or_default();
+    let params = parts
+        .map(|param| {
+            param
+                .parse::<f64>()
+                .map_err(|_| anyhow::anyhow!("invalid parameter {param} in {s}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let distribution = match (name, params.as_slice()) {
+        ("round-robin", []) => AccountDistribution::RoundRobin,
+        ("uniform", []) => AccountDistribution::Uniform,
+        ("zipf", [exponent]) => {
+            anyhow::ensure!(*exponent >= 0.0, "zipf exponent must not be negative");
+            AccountDistribution::Zipf {
+                exponent: *exponent,
+            }
+        }
+        ("hotspot", [hot_fraction, hot_share]) => {
+            anyhow::ensure!(
+                (0.0..=1.0).contains(hot_fraction) && (0.0..=1.0).contains(hot_share),
+                "hotspot parameters must be within [0, 1]"
+            );
+            AccountDistribution::Hotspot {
+                hot_fraction: *hot_fraction,
+                hot_share: *hot_share,
+            }
+        }
+        ("single-hot", []) => AccountDistribution::SingleHot,
+        _ => anyhow::bail!("invalid account distribution: {s}"),
+    };
+    Ok(distribution)
+}
+
+/// Samples indices of `num_accounts` accounts according to an [`AccountDistribution`].
+pub struct AccountSampler {
+    distribution: AccountDistribution,
+    num_accounts: usize,
+    /// Index returned next by [`AccountDistribution::RoundRobin`].
+    next_idx: usize,
+    /// Cumulative probabilities of [`AccountDistribution::Zipf`].
+    zipf_cdf: Vec<f64>,
+}
+
+impl AccountSampler {
+    pub fn new(distribution: AccountDistribution, num_accounts: usize) -> Self {
+        assert!(num_accounts > 0, "there must be accounts to sample from");
+        let zipf_cdf = match distribution {
+            AccountDistribution::Zipf { exponent } => {
+                let mut cdf = Vec::with_capacity(num_accounts);
+                let mut total = 0.0;
+                for k in 1..=num_accounts {
+                    total += 1.0 / (k as f64).powf(exponent);
+                    cdf.push(total);
+                }
+                cdf.iter_mut().for_each(|p| *p /= total);
+                cdf
+            }
+            _ => vec![],
+        };
+        Self {
+            distribution,
+            num_accounts,
+            next_idx: 0,
+            zipf_cdf,
+        }
+    }
+
+    pub fn sample(&mut self, rng: &mut impl Rng) -> usize {
+        match self.distribution {
+            AccountDistribution::RoundRobin => {
+                let idx = self.next_idx;
+                self.next_idx = (idx + 1) % self.num_accounts;
+                idx
+            }
+            AccountDistribution::Uniform => rng.gen_range(0..self.num_accounts),
+            AccountDistribution::Zipf { .. } => {
+                let p: f64 = rng.gen();
+                self.zipf_cdf
+                    .partition_point(|cdf| *cdf < p)
+                    .min(self.num_accounts - 1)
+            }
+            AccountDistribution::Hotspot {
+                hot_fraction,
+                hot_share,
+            } => {
+                let num_hot = ((self.num_accounts as f64 * hot_fraction).ceil() as usize)
+                    .clamp(1, self.num_accounts);
+                if num_hot == self.num_accounts || rng.gen_bool(hot_share) {
+                    rng.gen_range(0..num_hot)
+                } else {
+                    rng.gen_range(num_hot..self.num_accounts)
+                }
+            }
+            AccountDistribution::SingleHot => 0,
+        }
+    }
+}
+
+/// Samples pairs of distinct sender and receiver indices.
+pub struct AccountPairSampler {
+    senders: AccountSampler,
+    receivers: AccountSampler,
+    num_accounts: usize,
+}
+
+impl AccountPairSampler {
+    pub fn new(args: &AccountDistributionArgs, num_accounts: usize) -> Self {
+        assert!(
+            num_accounts >= 2,
+            "distinct pairs require at least two accounts"
+        );
+        Self {
+            senders: AccountSampler::new(args.sender_distribution, num_accounts),
+            receivers: AccountSampler::new(args.receiver_distribution, num_accounts),
+            num_accounts,
+        }
+    }
+
+    pub fn sample(&mut self, rng: &mut impl Rng) -> (usize, usize) {
+        let idx_sender = self.senders.sample(rng);
+        let mut idx_receiver = self.receivers.sample(rng);
+        if idx_receiver == idx_sender {
+            // Avoid creating a transaction where an account sends to itself.
+            idx_receiver = (idx_receiver + 1) % self.num_accounts;
+        }
+        (idx_sender, idx_receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_account_distribution() {
+        assert_eq!(
+            parse_account_distribution("zipf:1.1").unwrap(),
+            AccountDistribution::Zipf { exponent: 1.1 }
+        );
+        assert_eq!(
+            parse_account_distribution("hotspot:0.01:0.9").unwrap(),
+            AccountDistribution::Hotspot {
+                hot_fraction: 0.01,
+                hot_share: 0.9
+            }
+        );
+        assert!(parse_account_distribution("zipf").is_err());
+        assert!(parse_account_distribution("hotspot:2:0.5").is_err());
+        assert!(parse_account_distribution("uniform:1").is_err());
+        for s in [
+            "round-robin",
+            "uniform",
+            "zipf:1.1",
+            "hotspot:0.1:0.9",
+            "single-hot",
+        ] {
+            let distribution = parse_account_distribution(s).unwrap();
+            assert_eq!(distribution.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_skewed_distributions_favor_first_accounts() {
+        let mut rng = rand::thread_rng();
+        let num_samples = 10_000;
+        for distribution in [
+            AccountDistribution::Zipf { exponent: 1.2 },
+            AccountDistribution::Hotspot {
+                hot_fraction: 0.1,
+                hot_share: 0.9,
+            },
+        ] {
+            let mut sampler = AccountSampler::new(distribution, 100);
+            let num_in_first_ten = (0..num_samples)
+                .filter(|_| sampler.sample(&mut rng) < 10)
+                .count();
+            // Uniform sampling would put ~10% of samples into the first ten accounts.
+            assert!(
+                num_in_first_ten > num_samples / 2,
+                "{distribution}: {num_in_first_ten}"
+            );
+        }
+
+        let mut sampler = AccountSampler::new(AccountDistribution::RoundRobin, 3);
+        let indices: Vec<_> = (0..4).map(|_| sampler.sample(&mut rng)).collect();
+        assert_eq!(indices, vec![0, 1, 2, 0]);
+    }
+}
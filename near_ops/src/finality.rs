@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+use log::info;
+use near_jsonrpc_client::methods::{
+    send_tx::RpcSendTransactionRequest,
+    tx::{RpcTransactionStatusRequest, TransactionInfo},
+};
+use near_primitives::{hash::CryptoHash, transaction::SignedTransaction, views::TxExecutionStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    latency::LatencySummary, report::duration_ms, rpc::has_reached, rpc_client::RpcClient,
+    rpc_response_handler::RpcCallResult,
+};
+
+/// Finality levels for which the time to reach them is recorded, in ascending order.
+const RECORDED_LEVELS: [TxExecutionStatus; 3] = [
+    TxExecutionStatus::Included,
+    TxExecutionStatus::ExecutedOptimistic,
+    TxExecutionStatus::Final,
+];
+
+/// Time it took a transaction to reach finality levels, measured from sending it. Levels not
+/// implied by the requested `wait_until` are not awaited and hence `None`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinalityTimings {
+    pub tx_hash: CryptoHash,
+    #[serde(rename = "included_ms", with = "duration_ms::option")]
+    pub included: Option<Duration>,
+    #[serde(rename = "executed_optimistic_ms", with = "duration_ms::option")]
+    pub executed_optimistic: Option<Duration>,
+    #[serde(rename = "final_ms", with = "duration_ms::option")]
+    pub final_: Option<Duration>,
+}
+
+impl FinalityTimings {
+    fn new(tx_hash: CryptoHash) -> Self {
+        Self {
+            tx_hash,
+            included: None,
+            executed_optimistic: None,
+            final_: None,
+        }
+    }
+
+    fn record(&mut self, reached: &TxExecutionStatus, elapsed: Duration) {
+        for (level, timing) in RECORDED_LEVELS.iter().zip([
+            &mut self.included,
+            &mut self.executed_optimistic,
+            &mut self.final_,
+        ]) {
+            if timing.is_none() && has_reached(reached, level) {
+                *timing = Some(elapsed);
+            }
+        }
+    }
+}
+
+/// Sends a transaction and waits until it reaches `wait_until`, stepping through the finality
+/// levels in [`RECORDED_LEVELS`] to record when each of them is reached.
+///
+/// The first step is a `send_tx` request, subsequent steps are `tx` status requests. Hence up to
+/// three requests are made per transaction.
+pub async fn send_tx_and_record_finality(
+    client: &RpcClient,
+    signed_transaction: SignedTransaction,
+    wait_until: TxExecutionStatus,
+) -> (RpcCallResult, FinalityTimings) {
+    let tx_hash = signed_transaction.get_hash();
+    let sender_account_id = signed_transaction.transaction.signer_id().clone();
+    let mut timings = FinalityTimings::new(tx_hash);
+
+    let mut steps: Vec<TxExecutionStatus> = RECORDED_LEVELS
+        .iter()
+        .filter(|level| **level != wait_until && has_reached(&wait_until, level))
+        .cloned()
+        .collect();
+    steps.push(wait_until);
+
+    let sent_at = Instant::now();
+    let mut result = client
+        .call(RpcSendTransactionRequest {
+            signed_transaction,
+            wait_until: steps[0].clone(),
+        })
+        .await;
+    for step in steps.into_iter().skip(1) {
+        let response = match &result {
+            Ok(response) => response,
+            Err(_) => break,
+        };
+        timings.record(&response.final_execution_status, sent_at.elapsed());
+        if has_reached(&response.final_execution_status, &step) {
+            continue;
+        }
+        result = client
+            .call(RpcTransactionStatusRequest {
+                transaction_info: TransactionInfo::TransactionId {
+                    tx_hash,
+                    sender_account_id: sender_account_id.clone(),
+                },
+                wait_until: step,
+            })
+            .await;
+    }
+    if let Ok(response) = &result {
+        timings.record(&response.final_execution_status, sent_at.elapsed());
+    }
+
+    (result, timings)
+}
+
+/// Aggregates [`FinalityTimings`] of many transactions.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FinalityLatencyReport {
+    pub included: LatencySummary,
+    pub executed_optimistic: LatencySummary,
+    #[serde(rename = "final")]
+    pub final_: LatencySummary,
+    pub per_tx: Vec<FinalityTimings>,
+}
+
+impl FinalityLatencyReport {
+    pub fn new(per_tx: Vec<FinalityTimings>) -> Self {
+        let summarize = |get: fn(&FinalityTimings) -> Option<Duration>| {
+            let durations: Vec<Duration> = per_tx.iter().filter_map(get).collect();
+            LatencySummary::from_durations(&durations)
+        };
+        Self {
+            included: summarize(|timings| timings.included),
+            executed_optimistic: summarize(|timings| timings.executed_optimistic),
+            final_: summarize(|timings| timings.final_),
+            per_tx,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        info!("Latency until included: {}", self.included);
+        info!(
+            "Latency until executed optimistic: {}",
+            self.executed_optimistic
+        );
+        info!("Latency until final: {}", self.final_);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_fills_all_reached_levels() {
+        let mut timings = FinalityTimings::new(CryptoHash::default());
+        timings.record(&TxExecutionStatus::Included, Duration::from_millis(10));
+        // An `Executed` status implies `ExecutedOptimistic` but not `Final`.
+        timings.record(&TxExecutionStatus::Executed, Duration::from_millis(20));
+        assert_eq!(timings.included, Some(Duration::from_millis(10)));
+        assert_eq!(timings.executed_optimistic, Some(Duration::from_millis(20)));
+        assert_eq!(timings.final_, None);
+
+        // `IncludedFinal` does not imply execution.
+        let mut timings = FinalityTimings::new(CryptoHash::default());
+        timings.record(&TxExecutionStatus::IncludedFinal, Duration::from_millis(10));
+        assert_eq!(timings.included, Some(Duration::from_millis(10)));
+        assert_eq!(timings.executed_optimistic, None);
+    }
+}
@@ -0,0 +1,171 @@
+use log::info;
+use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
+use near_primitives::{
+    serialize::dec_format,
+    types::{Balance, Gas},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::block_scan::BlockScanReport;
+
+/// Gas and tokens burnt by an executed transaction, including all its receipts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TxCost {
+    pub gas_burnt: Gas,
+    pub tokens_burnt: Balance,
+}
+
+impl TxCost {
+    /// Returns `None` if the response contains no outcome, i.e. execution was not awaited.
+    pub fn from_response(response: &RpcTransactionResponse) -> Option<Self> {
+        let outcome = response.final_execution_outcome.clone()?.into_outcome();
+        let mut cost = Self {
+            gas_burnt: outcome.transaction_outcome.outcome.gas_burnt,
+            tokens_burnt: outcome.transaction_outcome.outcome.tokens_burnt,
+        };
+        for receipt_outcome in outcome.receipts_outcome.iter() {
+            cost.gas_burnt += receipt_outcome.outcome.gas_burnt;
+            cost.tokens_burnt += receipt_outcome.outcome.tokens_burnt;
+        }
+        Some(cost)
+    }
+}
+
+/// What the transactions of a run cost, to check fee-related protocol changes under load.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct GasReport {
+    /// Transactions whose outcome was received.
+    pub num_executed_txs: u64,
+    /// Summed in `u128` since the gas of many transactions exceeds [`Gas`].
+    #[serde(with = "dec_format")]
+    pub total_gas_burnt: u128,
+    #[serde(with = "dec_format")]
+    pub total_tokens_burnt: Balance,
+    pub mean_gas_burnt_per_tx: Gas,
+    #[serde(with = "dec_format")]
+    pub mean_tokens_burnt_per_tx: Balance,
+    /// Blocks the gas price was collected from, requires `--scan-blocks`.
+    pub num_blocks: u64,
+    #[serde(with = "dec_format")]
+    pub min_gas_price: Balance,
+    #[serde(with = "dec_format")]
+    pub max_gas_price: Balance,
+    #[serde(with = "dec_format")]
+    pub mean_gas_price: Balance,
+}
+
+impl GasReport {
+    /// Returns `None` if there are neither transaction costs nor scanned blocks.
+    pub fn new(tx_costs: &[TxCost], block_scan: Option<&BlockScanReport>) -> Option<Self> {
+        let gas_prices: Vec<Balance> = block_scan
+            .map(|block_scan| {
+                block_scan
+                    .blocks
+                    .iter()
+                    .map(|block| block.gas_price)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if tx_costs.is_empty() && gas_prices.is_empty() {
+            return None;
+        }
+
+        let mut report = Self {
+            num_executed_txs: u64::try_from(tx_costs.len()).unwrap(),
+            num_blocks: u64::try_from(gas_prices.len()).unwrap(),
+            ..Default::default()
+        };
+        for cost in tx_costs {
+            report.total_gas_burnt += u128::from(cost.gas_burnt);
+            report.total_tokens_burnt += cost.tokens_burnt;
+        }
+        report.mean_gas_burnt_per_tx = report
+            .total_gas_burnt
+            .checked_div(u128::from(report.num_executed_txs))
+            .map(|mean| Gas::try_from(mean).expect("mean is at most the gas burnt by one tx"))
+            .unwrap_or_default();
+        report.mean_tokens_burnt_per_tx = report
+            .total_tokens_burnt
+            .checked_div(Balance::from(report.num_executed_txs))
+            .unwrap_or_default();
+        report.min_gas_price = gas_prices.iter().copied().min().unwrap_or_default();
+        report.max_gas_price = gas_prices.iter().copied().max().unwrap_or_default();
+        report.mean_gas_price = gas_prices
+            .iter()
+            .sum::<Balance>()
+            .checked_div(Balance::from(report.num_blocks))
+            .unwrap_or_default();
+        Some(report)
+    }
+
+    pub fn log_summary(&self) {
+        if self.num_executed_txs > 0 {
+            info!(
+                "Gas burnt by {} txs: {} in total, {} per tx; tokens burnt: {} in total, {} per tx",
+                self.num_executed_txs,
+                self.total_gas_burnt,
+                self.mean_gas_burnt_per_tx,
+                self.total_tokens_burnt,
+                self.mean_tokens_burnt_per_tx
+            );
+        }
+        if self.num_blocks > 0 {
+            info!(
+                "Gas price over {} blocks: min {}, mean {}, max {}",
+                self.num_blocks, self.min_gas_price, self.mean_gas_price, self.max_gas_price
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block_scan::BlockStats;
+
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let costs = [
+            TxCost {
+                gas_burnt: 100,
+                tokens_burnt: 1000,
+            },
+            TxCost {
+                gas_burnt: 300,
+                tokens_burnt: 3000,
+            },
+        ];
+        let block = |gas_price| BlockStats {
+            height: 1,
+            timestamp_ms: 0,
+            gas_price,
+            chunks: vec![],
+        };
+        let block_scan = BlockScanReport::new(vec![block(10), block(30), block(20)], 1.0);
+        let report = GasReport::new(&costs, Some(&block_scan)).unwrap();
+        assert_eq!(
+            report,
+            GasReport {
+                num_executed_txs: 2,
+                total_gas_burnt: 400,
+                total_tokens_burnt: 4000,
+                mean_gas_burnt_per_tx: 200,
+                mean_tokens_burnt_per_tx: 2000,
+                num_blocks: 3,
+                min_gas_price: 10,
+                max_gas_price: 30,
+                mean_gas_price: 20,
+            }
+        );
+        assert_eq!(GasReport::new(&[], None), None);
+
+        let expensive = TxCost {
+            gas_burnt: Gas::MAX,
+            tokens_burnt: 0,
+        };
+        let report = GasReport::new(&[expensive, expensive], None).unwrap();
+        assert_eq!(report.total_gas_burnt, 2 * u128::from(Gas::MAX));
+        assert_eq!(report.mean_gas_burnt_per_tx, Gas::MAX);
+    }
+}
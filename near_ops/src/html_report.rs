@@ -0,0 +1,765 @@
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use near_primitives::types::ShardId;
+
+use crate::{
+    block_scan::{BlockScanReport, ChunkStats},
+    congestion::ChunkCongestion,
+    latency::LatencySummary,
+    report::BenchmarkReport,
+    throughput::ThroughputSample,
+};
+
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 280.0;
+/// Space reserved for axis labels, in the order top, right, bottom, left.
+const MARGIN: (f64, f64, f64, f64) = (20.0, 20.0, 45.0, 70.0);
+const NUM_TICKS: usize = 5;
+const NUM_LATENCY_BINS: usize = 20;
+const COLORS: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+];
+
+const STYLE: &str =
+    "body { font-family: sans-serif; margin: 2em auto; max-width: 860px; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+td, th { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+svg { display: block; margin-bottom: 1.5em; }
+svg text { font-size: 12px; }";
+
+/// A named sequence of `(x, y)` points.
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    fn new(name: &str, points: Vec<(f64, f64)>) -> Self {
+        Self {
+            name: name.to_string(),
+            points,
+        }
+    }
+}
+
+/// Renders `report` as a self-contained HTML page. Charts are inline SVG, hence the page can be
+/// shared as a single file and opened without network access.
+pub fn render_html(report: &BenchmarkReport, title: &str) -> String {
+    let mut body = String::new();
+    writeln!(body, "<h1>{}</h1>", escape(title)).unwrap();
+    render_summary(report, &mut body);
+    render_throughput(report, &mut body);
+    render_latency(report, &mut body);
+    render_block_scan(report, &mut body);
+    render_gas(report, &mut body);
+    render_node_metrics(report, &mut body);
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+fn render_summary(report: &BenchmarkReport, out: &mut String) {
+    let send_secs = report.send_duration.as_secs_f64();
+    let mut rows = vec![];
+    if let Some(num_txs_sent) = report.num_txs_sent {
+        rows.push(("Transactions sent", num_txs_sent.to_string()));
+    }
+    rows.push(("Send duration", format!("{send_secs:.2} s")));
+    if let Some(num_txs_sent) = report.num_txs_sent {
+        let rate = num_txs_sent as f64 / send_secs.max(f64::EPSILON);
+        rows.push(("Send rate", format!("{rate:.1} tx/s")));
+    }
+    if let Some(queries) = &report.queries {
+        let rate = queries.num_sent as f64 / send_secs.max(f64::EPSILON);
+        rows.push(("Queries sent", queries.num_sent.to_string()));
+        rows.push(("Query rate", format!("{rate:.1} /s")));
+    }
+    if let Some(wait_until) = &report.wait_until {
+        rows.push(("Wait until", format!("{wait_until:?}")));
+    }
+    rows.push(("RPC connections", report.connection_stats.to_string()));
+    if let Some(seed) = report.seed {
+        rows.push(("Seed", seed.to_string()));
+    }
+    if let Some(distribution) = &report.sender_distribution {
+        rows.push(("Sender distribution", distribution.clone()));
+    }
+    if let Some(distribution) = &report.receiver_distribution {
+        rows.push(("Receiver distribution", distribution.clone()));
+    }
+    if let Some(verification) = &report.verification {
+        let result = if verification.is_ok() { "ok" } else { "failed" };
+        rows.push(("Verification", result.to_string()));
+    }
+    if let Some(workload) = &report.workload {
+        let num_sent = workload
+            .num_sent
+            .iter()
+            .map(|(kind, num_sent)| format!("{kind}: {num_sent}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        rows.push(("Sent by kind", num_sent));
+    }
+    if let Some(deploy) = &report.deploy {
+        for (code_size, code_size_report) in deploy.per_code_size.iter() {
+            rows.push((
+                "Deployments",
+                format!(
+                    "{code_size} bytes: {} sent, {} succeeded, execution p50 {:.2?}, p99 {:.2?}",
+                    code_size_report.num_sent,
+                    code_size_report.num_succeeded,
+                    code_size_report.execution_latency.p50,
+                    code_size_report.execution_latency.p99
+                ),
+            ));
+        }
+    }
+    if let Some(queries) = &report.queries {
+        for (kind, query_report) in queries.per_kind.iter() {
+            rows.push((
+                "Queries",
+                format!(
+                    "{kind}: {} sent, {} errors, p50 {:.2?}, p99 {:.2?}",
+                    query_report.num_sent,
+                    query_report.num_errors,
+                    query_report.latency.p50,
+                    query_report.latency.p99
+                ),
+            ));
+        }
+    }
+
+    writeln!(out, "<h2>Summary</h2>\n<table>").unwrap();
+    for (name, value) in rows {
+        writeln!(
+            out,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(name),
+            escape(&value)
+        )
+        .unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+}
+
+fn render_throughput(report: &BenchmarkReport, out: &mut String) {
+    let samples = match &report.throughput {
+        Some(samples) if !samples.is_empty() => samples,
+        _ => return,
+    };
+    let series = |name: &str, value: fn(&ThroughputSample) -> f64| {
+        Series::new(
+            name,
+            samples
+                .iter()
+                .map(|sample| (sample.second as f64, value(sample)))
+                .collect(),
+        )
+    };
+    writeln!(out, "<h2>Throughput</h2>").unwrap();
+    out.push_str(&line_chart(
+        "Transactions per second",
+        "second",
+        "tx/s",
+        &[
+            series("sent", |sample| sample.sent as f64),
+            series("responded", |sample| sample.responded as f64),
+            series("succeeded", |sample| sample.succeeded as f64),
+            series("failed", |sample| sample.failed as f64),
+        ],
+    ));
+    out.push_str(&line_chart(
+        "Requests in flight",
+        "second",
+        "requests",
+        &[series("in flight", |sample| sample.in_flight as f64)],
+    ));
+}
+
+fn render_latency(report: &BenchmarkReport, out: &mut String) {
+    let mut summaries: Vec<(&str, &LatencySummary)> = vec![];
+    let mut latencies: Vec<Duration> = vec![];
+    if let Some(finality) = &report.finality_latency {
+        summaries.push(("Included", &finality.included));
+        summaries.push(("Executed optimistic", &finality.executed_optimistic));
+        summaries.push(("Final", &finality.final_));
+        latencies = finality
+            .per_tx
+            .iter()
+            .filter_map(|timings| {
+                timings
+                    .final_
+                    .or(timings.executed_optimistic)
+                    .or(timings.included)
+            })
+            .collect();
+    }
+    if let Some(tx_status) = &report.tx_status {
+        summaries.push(("Inclusion (polled)", &tx_status.inclusion_latency));
+        summaries.push(("Execution (polled)", &tx_status.execution_latency));
+    }
+    if let Some(tx_lifecycle) = &report.tx_lifecycle {
+        summaries.push(("Inclusion (block scan)", &tx_lifecycle.inclusion_latency));
+        summaries.push(("Execution (block scan)", &tx_lifecycle.execution_latency));
+        if latencies.is_empty() {
+            latencies = tx_lifecycle
+                .per_tx
+                .iter()
+                .filter_map(|tx| tx.execution_latency)
+                .collect();
+        }
+    }
+    if let Some(meta_tx) = &report.meta_tx {
+        summaries.push(("Relayer tx inclusion", &meta_tx.inclusion_latency));
+        summaries.push(("Delegate execution", &meta_tx.execution_latency));
+    }
+    summaries.retain(|(_, summary)| summary.count > 0);
+    if summaries.is_empty() {
+        return;
+    }
+
+    writeln!(out, "<h2>Latency</h2>\n<table>").unwrap();
+    writeln!(
+        out,
+        "<tr><th></th><th>n</th><th>min</th><th>mean</th><th>p50</th><th>p90</th><th>p99</th><th>max</th></tr>"
+    )
+    .unwrap();
+    for (name, summary) in summaries {
+        write!(
+            out,
+            "<tr><td>{}</td><td>{}</td>",
+            escape(name),
+            summary.count
+        )
+        .unwrap();
+        for duration in [
+            summary.min,
+            summary.mean,
+            summary.p50,
+            summary.p90,
+            summary.p99,
+            summary.max,
+        ] {
+            write!(out, "<td>{:.0} ms</td>", duration.as_secs_f64() * 1000.0).unwrap();
+        }
+        writeln!(out, "</tr>").unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    if !latencies.is_empty() {
+        out.push_str(&bar_chart(
+            "Distribution of the latency until the awaited finality level",
+            "ms",
+            "transactions",
+            &latency_histogram(&latencies),
+        ));
+    }
+}
+
+/// Counts `latencies` in equally wide bins, labeled by their upper bound in milliseconds.
+fn latency_histogram(latencies: &[Duration]) -> Vec<(String, f64)> {
+    let max_ms = latencies
+        .iter()
+        .map(|latency| latency.as_secs_f64() * 1000.0)
+        .fold(0.0, f64::max);
+    let bin_width = (max_ms / NUM_LATENCY_BINS as f64).max(1.0);
+    let mut counts = vec![0u64; NUM_LATENCY_BINS];
+    for latency in latencies {
+        let bin = (latency.as_secs_f64() * 1000.0 / bin_width) as usize;
+        counts[bin.min(NUM_LATENCY_BINS - 1)] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| (format!("{:.0}", (bin + 1) as f64 * bin_width), count as f64))
+        .collect()
+}
+
+fn render_block_scan(report: &BenchmarkReport, out: &mut String) {
+    if let Some(block_scan) = &report.block_scan {
+        render_chunk_fullness(block_scan, out);
+        render_congestion(block_scan, out);
+    }
+    let tx_lifecycle = match &report.tx_lifecycle {
+        Some(tx_lifecycle) => tx_lifecycle,
+        None => return,
+    };
+    let mut included_per_height: BTreeMap<u64, u64> = BTreeMap::new();
+    for tx in tx_lifecycle.per_tx.iter() {
+        if let Some(height) = tx.included_height {
+            *included_per_height.entry(height).or_default() += 1;
+        }
+    }
+    if included_per_height.is_empty() {
+        return;
+    }
+    writeln!(out, "<h2>Blocks</h2>").unwrap();
+    out.push_str(&line_chart(
+        "Benchmark transactions included per block",
+        "block height",
+        "transactions",
+        &[Series::new(
+            "included",
+            included_per_height
+                .into_iter()
+                .map(|(height, count)| (height as f64, count as f64))
+                .collect(),
+        )],
+    ));
+}
+
+fn render_chunk_fullness(block_scan: &BlockScanReport, out: &mut String) {
+    if block_scan.blocks.is_empty() {
+        return;
+    }
+    writeln!(out, "<h2>Chunks</h2>\n<table>").unwrap();
+    for (name, value) in [
+        ("Blocks", block_scan.num_blocks.to_string()),
+        ("Chunks", block_scan.num_chunks.to_string()),
+        ("Missing chunks", block_scan.num_missing_chunks.to_string()),
+        (
+            "Full chunks (>= 90% gas)",
+            block_scan.num_full_chunks.to_string(),
+        ),
+        (
+            "Mean gas utilization",
+            format!("{:.1}%", block_scan.mean_gas_utilization * 100.0),
+        ),
+    ] {
+        writeln!(out, "<tr><th>{}</th><td>{value}</td></tr>", escape(name)).unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    // One series per shard, skipping heights at which its chunk is missing.
+    let per_shard = |value: fn(&ChunkStats) -> f64| {
+        let mut series: BTreeMap<ShardId, Vec<(f64, f64)>> = BTreeMap::new();
+        for block in block_scan.blocks.iter() {
+            for chunk in block.chunks.iter().filter(|chunk| !chunk.missing) {
+                series
+                    .entry(chunk.shard_id)
+                    .or_default()
+                    .push((block.height as f64, value(chunk)));
+            }
+        }
+        series
+            .into_iter()
+            .map(|(shard_id, points)| Series::new(&format!("shard {shard_id}"), points))
+            .collect::<Vec<_>>()
+    };
+    out.push_str(&line_chart(
+        "Chunk gas used / gas limit",
+        "block height",
+        "%",
+        &per_shard(|chunk| chunk.gas_utilization() * 100.0),
+    ));
+    out.push_str(&line_chart(
+        "Transactions per chunk",
+        "block height",
+        "transactions",
+        &per_shard(|chunk| chunk.num_transactions as f64),
+    ));
+    out.push_str(&line_chart(
+        "Incoming receipts per chunk",
+        "block height",
+        "receipts",
+        &per_shard(|chunk| chunk.num_incoming_receipts as f64),
+    ));
+    out.push_str(&line_chart(
+        "Missing chunks",
+        "block height",
+        "chunks",
+        &[Series::new(
+            "missing",
+            block_scan
+                .blocks
+                .iter()
+                .map(|block| {
+                    let num_missing = block.chunks.iter().filter(|chunk| chunk.missing).count();
+                    (block.height as f64, num_missing as f64)
+                })
+                .collect(),
+        )],
+    ));
+}
+
+fn render_congestion(block_scan: &BlockScanReport, out: &mut String) {
+    if block_scan.congestion.is_empty() {
+        return;
+    }
+    writeln!(
+        out,
+        "<h2>Congestion</h2>\n<table>\n<tr><th>Shard</th><th>Max level</th><th>Chunks with queued receipts</th><th>Queued at heights</th><th>Chunks rejecting txs</th><th>Rejecting at heights</th></tr>"
+    )
+    .unwrap();
+    let heights = |first: Option<u64>, last: Option<u64>| match (first, last) {
+        (Some(first), Some(last)) => format!("{first} - {last}"),
+        _ => "-".to_string(),
+    };
+    for shard in block_scan.congestion.iter() {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            shard.shard_id,
+            shard.max_level,
+            shard.num_chunks_with_queued_receipts,
+            heights(shard.first_queued_height, shard.last_queued_height),
+            shard.num_rejecting_chunks,
+            heights(shard.first_rejecting_height, shard.last_rejecting_height),
+        )
+        .unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    let per_shard = |value: fn(&ChunkCongestion) -> f64| {
+        let mut series: BTreeMap<ShardId, Vec<(f64, f64)>> = BTreeMap::new();
+        for block in block_scan.blocks.iter() {
+            for chunk in block.chunks.iter() {
+                if let Some(congestion) = &chunk.congestion {
+                    series
+                        .entry(chunk.shard_id)
+                        .or_default()
+                        .push((block.height as f64, value(congestion)));
+                }
+            }
+        }
+        series
+            .into_iter()
+            .map(|(shard_id, points)| Series::new(&format!("shard {shard_id}"), points))
+            .collect::<Vec<_>>()
+    };
+    out.push_str(&line_chart(
+        "Congestion level",
+        "block height",
+        "level",
+        &per_shard(|congestion| congestion.level),
+    ));
+    out.push_str(&line_chart(
+        "Delayed receipts gas",
+        "block height",
+        "Tgas",
+        &per_shard(|congestion| congestion.delayed_receipts_gas as f64 / 1e12),
+    ));
+    out.push_str(&line_chart(
+        "Buffered receipts gas",
+        "block height",
+        "Tgas",
+        &per_shard(|congestion| congestion.buffered_receipts_gas as f64 / 1e12),
+    ));
+}
+
+fn render_gas(report: &BenchmarkReport, out: &mut String) {
+    let gas = match &report.gas {
+        Some(gas) => gas,
+        None => return,
+    };
+    writeln!(out, "<h2>Gas</h2>\n<table>").unwrap();
+    let mut rows = vec![];
+    if gas.num_executed_txs > 0 {
+        rows.extend([
+            ("Executed transactions", gas.num_executed_txs.to_string()),
+            ("Gas burnt", gas.total_gas_burnt.to_string()),
+            ("Gas burnt per tx", gas.mean_gas_burnt_per_tx.to_string()),
+            ("Tokens burnt", gas.total_tokens_burnt.to_string()),
+            (
+                "Tokens burnt per tx",
+                gas.mean_tokens_burnt_per_tx.to_string(),
+            ),
+        ]);
+    }
+    if gas.num_blocks > 0 {
+        rows.extend([
+            ("Min gas price", gas.min_gas_price.to_string()),
+            ("Mean gas price", gas.mean_gas_price.to_string()),
+            ("Max gas price", gas.max_gas_price.to_string()),
+        ]);
+    }
+    for (name, value) in rows {
+        writeln!(out, "<tr><th>{}</th><td>{value}</td></tr>", escape(name)).unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    if let Some(block_scan) = &report.block_scan {
+        if !block_scan.blocks.is_empty() {
+            out.push_str(&line_chart(
+                "Gas price",
+                "block height",
+                "yoctoNEAR per gas",
+                &[Series::new(
+                    "gas price",
+                    block_scan
+                        .blocks
+                        .iter()
+                        .map(|block| (block.height as f64, block.gas_price as f64))
+                        .collect(),
+                )],
+            ));
+        }
+    }
+}
+
+fn render_node_metrics(report: &BenchmarkReport, out: &mut String) {
+    let node_metrics = match &report.node_metrics {
+        Some(node_metrics) if !node_metrics.samples.is_empty() => node_metrics,
+        _ => return,
+    };
+    let start_ms = node_metrics.samples[0].timestamp_ms;
+    let mut series: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+    for sample in node_metrics.samples.iter() {
+        let second = sample.timestamp_ms.saturating_sub(start_ms) as f64 / 1000.0;
+        for (name, value) in sample.values.iter() {
+            series.entry(name).or_default().push((second, *value));
+        }
+    }
+    writeln!(
+        out,
+        "<h2>Node metrics</h2>\n<p>Scraped from {}</p>",
+        escape(&node_metrics.url)
+    )
+    .unwrap();
+    for (name, points) in series {
+        out.push_str(&line_chart(
+            name,
+            "second",
+            "",
+            &[Series::new(name, points)],
+        ));
+    }
+}
+
+/// Maps values in `[min, max]` to pixels in `[from, to]`.
+struct Scale {
+    min: f64,
+    max: f64,
+    from: f64,
+    to: f64,
+}
+
+impl Scale {
+    fn new(min: f64, max: f64, from: f64, to: f64) -> Self {
+        // Avoid division by zero for constant values.
+        let max = if max > min { max } else { min + 1.0 };
+        Self { min, max, from, to }
+    }
+
+    fn map(&self, value: f64) -> f64 {
+        self.from + (value - self.min) / (self.max - self.min) * (self.to - self.from)
+    }
+
+    fn ticks(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..=NUM_TICKS).map(|i| self.min + (self.max - self.min) * i as f64 / NUM_TICKS as f64)
+    }
+}
+
+/// Opens an `svg` element with a title, a frame and labeled y ticks. Returns the y scale.
+fn chart_frame(out: &mut String, title: &str, x_label: &str, y_label: &str, y_max: f64) -> Scale {
+    let (top, right, bottom, left) = MARGIN;
+    let y_scale = Scale::new(0.0, y_max, CHART_HEIGHT - bottom, top);
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_WIDTH}\" height=\"{}\" viewBox=\"0 0 {CHART_WIDTH} {}\">",
+        CHART_HEIGHT + 20.0,
+        CHART_HEIGHT + 20.0
+    )
+    .unwrap();
+    writeln!(out, "<title>{}</title>", escape(title)).unwrap();
+    writeln!(
+        out,
+        "<text x=\"{left}\" y=\"14\" font-weight=\"bold\">{}</text>",
+        escape(title)
+    )
+    .unwrap();
+    for tick in y_scale.ticks() {
+        let y = y_scale.map(tick);
+        writeln!(
+            out,
+            "<line x1=\"{left}\" y1=\"{y:.1}\" x2=\"{}\" y2=\"{y:.1}\" stroke=\"#eee\"/><text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            CHART_WIDTH - right,
+            left - 6.0,
+            y + 4.0,
+            format_number(tick)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "<rect x=\"{left}\" y=\"{top}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#999\"/>",
+        CHART_WIDTH - left - right,
+        CHART_HEIGHT - top - bottom
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+        left + (CHART_WIDTH - left - right) / 2.0,
+        CHART_HEIGHT + 10.0,
+        escape(x_label)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "<text x=\"14\" y=\"{}\" transform=\"rotate(-90 14 {})\" text-anchor=\"middle\">{}</text>",
+        CHART_HEIGHT / 2.0,
+        CHART_HEIGHT / 2.0,
+        escape(y_label)
+    )
+    .unwrap();
+    y_scale
+}
+
+pub fn line_chart(title: &str, x_label: &str, y_label: &str, series: &[Series]) -> String {
+    let (_, right, bottom, left) = MARGIN;
+    let points = || series.iter().flat_map(|series| series.points.iter());
+    let x_min = points().map(|(x, _)| *x).fold(f64::INFINITY, f64::min);
+    let x_max = points().map(|(x, _)| *x).fold(f64::NEG_INFINITY, f64::max);
+    let y_max = points().map(|(_, y)| *y).fold(0.0, f64::max);
+    if !x_min.is_finite() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let y_scale = chart_frame(&mut out, title, x_label, y_label, y_max);
+    let x_scale = Scale::new(x_min, x_max, left, CHART_WIDTH - right);
+    for tick in x_scale.ticks() {
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            x_scale.map(tick),
+            CHART_HEIGHT - bottom + 16.0,
+            format_number(tick)
+        )
+        .unwrap();
+    }
+    for (idx, line) in series.iter().enumerate() {
+        let color = COLORS[idx % COLORS.len()];
+        let points = line
+            .points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x_scale.map(*x), y_scale.map(*y)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\"/>"
+        )
+        .unwrap();
+        if series.len() > 1 {
+            let x = left + 10.0 + 120.0 * idx as f64;
+            writeln!(
+                out,
+                "<rect x=\"{x}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{color}\"/><text x=\"{}\" y=\"{}\">{}</text>",
+                MARGIN.0 + 6.0,
+                x + 14.0,
+                MARGIN.0 + 15.0,
+                escape(&line.name)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+pub fn bar_chart(title: &str, x_label: &str, y_label: &str, bars: &[(String, f64)]) -> String {
+    if bars.is_empty() {
+        return String::new();
+    }
+    let (_, right, bottom, left) = MARGIN;
+    let y_max = bars.iter().map(|(_, y)| *y).fold(0.0, f64::max);
+
+    let mut out = String::new();
+    let y_scale = chart_frame(&mut out, title, x_label, y_label, y_max);
+    let bar_width = (CHART_WIDTH - left - right) / bars.len() as f64;
+    // Label only some bars to avoid overlapping labels.
+    let label_every = bars.len().div_ceil(10);
+    for (idx, (label, y)) in bars.iter().enumerate() {
+        let x = left + bar_width * idx as f64;
+        let bar_top = y_scale.map(*y);
+        writeln!(
+            out,
+            "<rect x=\"{:.1}\" y=\"{bar_top:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+            x + 1.0,
+            (bar_width - 2.0).max(1.0),
+            CHART_HEIGHT - bottom - bar_top,
+            COLORS[0]
+        )
+        .unwrap();
+        if idx % label_every == 0 {
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x + bar_width / 2.0,
+                CHART_HEIGHT - bottom + 16.0,
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+fn format_number(value: f64) -> String {
+    if value.abs() >= 1e6 {
+        format!("{:.1}M", value / 1e6)
+    } else if value.abs() >= 1e4 {
+        format!("{:.1}k", value / 1e3)
+    } else if value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let report = BenchmarkReport {
+            num_txs_sent: Some(20),
+            send_duration: Duration::from_secs(2),
+            seed: Some(7),
+            throughput: Some(
+                (0..2)
+                    .map(|second| ThroughputSample {
+                        second,
+                        sent: 10,
+                        responded: 9,
+                        succeeded: 8,
+                        failed: 1,
+                        in_flight: 3,
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let html = render_html(&report, "run <1>");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>run &lt;1&gt;</h1>"));
+        assert!(html.contains("<tr><th>Send rate</th><td>10.0 tx/s</td></tr>"));
+        assert!(html.contains("<h2>Throughput</h2>"));
+        assert_eq!(html.matches("<svg").count(), 2);
+        // Sections without data are omitted.
+        assert!(!html.contains("<h2>Latency</h2>"));
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        let bars = latency_histogram(&latencies);
+        assert_eq!(bars.len(), NUM_LATENCY_BINS);
+        assert_eq!(bars[0], ("5".to_string(), 4.0));
+        assert_eq!(bars.iter().map(|(_, count)| count).sum::<f64>(), 100.0);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use clap::Args;
+use log::info;
+
+use crate::{
+    block_scan::{BlockScanArgs, BlockScanHandle, BlockScanReport, BlockScanner},
+    block_service::BlockService,
+    dashboard::{Dashboard, DashboardHandle},
+    gas::{GasReport, TxCost},
+    node_metrics::{NodeMetricsArgs, NodeMetricsCollector, NodeMetricsHandle, NodeMetricsReport},
+    report::BenchmarkReport,
+    rpc_client::RpcClient,
+    throughput::{write_csv, ThroughputRecorder, ThroughputSample},
+};
+
+/// Observes a benchmark run besides the requests it sends. Shared by all benchmarks.
+#[derive(Args, Debug)]
+pub struct InstrumentationArgs {
+    /// Shows a live dashboard of the run in the terminal. Logs are still written to stderr and
+    /// should be redirected, e.g. with `2> benchmark.log`.
+    #[arg(long)]
+    pub tui: bool,
+    #[command(flatten)]
+    pub node_metrics: NodeMetricsArgs,
+    #[command(flatten)]
+    pub block_scan: BlockScanArgs,
+    /// If provided, per-second counts of sent, responded, succeeded and failed requests and of
+    /// requests in flight are written to this CSV file.
+    #[arg(long)]
+    pub time_series_path: Option<PathBuf>,
+}
+
+/// Instrumentation running in the background, see [`Instrumentation::start`].
+pub struct Instrumentation {
+    node_metrics: Option<NodeMetricsHandle>,
+    block_scan: Option<BlockScanHandle>,
+    throughput: ThroughputRecorder,
+    dashboard: Option<DashboardHandle>,
+    time_series_path: Option<PathBuf>,
+}
+
+/// The sections of [`BenchmarkReport`] produced by [`Instrumentation`].
+pub struct InstrumentationReport {
+    pub node_metrics: Option<NodeMetricsReport>,
+    pub block_scan: Option<BlockScanReport>,
+    pub gas: Option<GasReport>,
+    pub throughput: Vec<ThroughputSample>,
+}
+
+impl Instrumentation {
+    /// Starts the instrumentation enabled by `args`. The dashboard shows blocks of
+    /// `block_service`, or of a new one if `None` is passed. `interval_duration` is the interval
+    /// at which the benchmark sends requests.
+    pub async fn start(
+        client: &RpcClient,
+        args: &InstrumentationArgs,
+        block_service: Option<Arc<BlockService>>,
+        interval_duration: Duration,
+    ) -> anyhow::Result<Self> {
+        let node_metrics =
+            NodeMetricsCollector::from_args(&args.node_metrics)?.map(NodeMetricsCollector::spawn);
+        let block_scan = BlockScanner::from_args(client.clone(), &args.block_scan)
+            .await?
+            .map(BlockScanner::spawn);
+        let throughput = ThroughputRecorder::spawn();
+        let dashboard = if args.tui {
+            let block_service = match block_service {
+                Some(block_service) => block_service,
+                None => {
+                    let block_service = Arc::new(BlockService::new(client.clone()).await);
+                    block_service.clone().start().await;
+                    block_service
+                }
+            };
+            Some(Dashboard::new(block_service, interval_duration).spawn())
+        } else {
+            None
+        };
+        Ok(Self {
+            node_metrics,
+            block_scan,
+            throughput,
+            dashboard,
+            time_series_path: args.time_series_path.clone(),
+        })
+    }
+
+    /// Stops the instrumentation, logs summaries and writes the time series. `tx_costs` of the
+    /// transactions sent are combined with the scanned blocks into the gas report.
+    pub async fn finish(self, tx_costs: &[TxCost]) -> anyhow::Result<InstrumentationReport> {
+        if let Some(dashboard) = self.dashboard {
+            dashboard.stop().await;
+        }
+        let throughput = self.throughput.stop().await;
+        if let Some(path) = &self.time_series_path {
+            write_csv(&throughput, path)?;
+            info!("Wrote time series to {:?}", path);
+        }
+        let block_scan = match self.block_scan {
+            Some(scanner) => {
+                let block_scan = scanner.stop().await;
+                block_scan.log_summary();
+                Some(block_scan)
+            }
+            None => None,
+        };
+        let gas = GasReport::new(tx_costs, block_scan.as_ref());
+        if let Some(gas) = &gas {
+            gas.log_summary();
+        }
+        let node_metrics = match self.node_metrics {
+            Some(collector) => {
+                let node_metrics = collector.stop().await;
+                node_metrics.log_summary();
+                Some(node_metrics)
+            }
+            None => None,
+        };
+        Ok(InstrumentationReport {
+            node_metrics,
+            block_scan,
+            gas,
+            throughput,
+        })
+    }
+}
+
+impl From<InstrumentationReport> for BenchmarkReport {
+    fn from(report: InstrumentationReport) -> Self {
+        Self {
+            node_metrics: report.node_metrics,
+            block_scan: report.block_scan,
+            gas: report.gas,
+            throughput: Some(report.throughput),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::duration_ms;
+
+/// Summarizes a set of latency measurements.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LatencySummary {
+    pub count: usize,
+    #[serde(rename = "min_ms", with = "duration_ms")]
+    pub min: Duration,
+    #[serde(rename = "mean_ms", with = "duration_ms")]
+    pub mean: Duration,
+    #[serde(rename = "p50_ms", with = "duration_ms")]
+    pub p50: Duration,
+    #[serde(rename = "p90_ms", with = "duration_ms")]
+    pub p90: Duration,
+    #[serde(rename = "p99_ms", with = "duration_ms")]
+    pub p99: Duration,
+    #[serde(rename = "max_ms", with = "duration_ms")]
+    pub max: Duration,
+}
+
+impl LatencySummary {
+    pub fn from_durations(durations: &[Duration]) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = durations.to_vec();
+        sorted.sort_unstable();
+        let total: Duration = sorted.iter().sum();
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            mean: total / u32::try_from(sorted.len()).unwrap(),
+            p50: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            p99: percentile(&sorted, 99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Nearest-rank percentile of a non-empty, sorted slice.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} min={:.2?} mean={:.2?} p50={:.2?} p90={:.2?} p99={:.2?} max={:.2?}",
+            self.count, self.min, self.mean, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_durations() {
+        let durations: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let summary = LatencySummary::from_durations(&durations);
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.mean, Duration::from_micros(50_500));
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p90, Duration::from_millis(90));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+
+        assert_eq!(
+            LatencySummary::from_durations(&[]),
+            LatencySummary::default()
+        );
+    }
+}
@@ -1,7 +1,30 @@
 pub mod account;
+pub mod block_scan;
 pub mod block_service;
+pub mod congestion;
 pub mod contract;
+pub mod dashboard;
+pub mod deploy;
+pub mod distribution;
+pub mod finality;
+pub mod gas;
+pub mod html_report;
+pub mod instrumentation;
+pub mod latency;
+pub mod load_metrics;
+pub mod meta_tx;
+pub mod node_metrics;
+pub mod query;
+pub mod report;
+pub mod rng;
 pub mod rpc;
+pub mod rpc_client;
 pub mod rpc_response_handler;
+pub mod send_loop;
 #[cfg(test)]
 mod test_utils;
+pub mod throughput;
+pub mod tx_lifecycle;
+pub mod tx_status_tracker;
+pub mod verification;
+pub mod workload;
@@ -0,0 +1,406 @@
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        LazyLock,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use near_jsonrpc_client::{errors::JsonRpcError, methods::tx::RpcTransactionError};
+use near_primitives::errors::InvalidTxError;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    rpc::is_transaction_and_receipts_success, rpc_response_handler::RpcCallResult,
+    tx_status_tracker::BroadcastTxAsyncResult,
+};
+
+/// Upper bounds (in seconds) of the response latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+static LOAD_METRICS: LazyLock<LoadMetrics> = LazyLock::new(LoadMetrics::default);
+
+/// Metrics of the load generated by this process, shared by all benchmarks.
+pub fn load_metrics() -> &'static LoadMetrics {
+    &LOAD_METRICS
+}
+
+/// Category of the response to a transaction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResponseOutcome {
+    /// The transaction was accepted and, if the response contains its outcome, it succeeded.
+    Succeeded,
+    /// The transaction or one of its receipts failed.
+    Failed,
+    /// The transaction was rejected due to the nonce of its access key.
+    NonceError,
+    /// The RPC node timed out handling the request, or the request itself timed out.
+    Timeout,
+    OtherRpcError,
+}
+
+impl ResponseOutcome {
+    const ALL: [Self; 5] = [
+        Self::Succeeded,
+        Self::Failed,
+        Self::NonceError,
+        Self::Timeout,
+        Self::OtherRpcError,
+    ];
+
+    pub fn of_send_tx(result: &RpcCallResult) -> Self {
+        match result {
+            Ok(response) if response.final_execution_outcome.is_none() => Self::Succeeded,
+            Ok(response) if is_transaction_and_receipts_success(response) => Self::Succeeded,
+            Ok(_) => Self::Failed,
+            Err(err) => match err.handler_error() {
+                Some(RpcTransactionError::InvalidTransaction {
+                    context:
+                        InvalidTxError::InvalidNonce { .. } | InvalidTxError::NonceTooLarge { .. },
+                }) => Self::NonceError,
+                Some(RpcTransactionError::TimeoutError) => Self::Timeout,
+                _ => Self::of_rpc_error(err),
+            },
+        }
+    }
+
+    /// Only tells whether the transaction was accepted, since the response carries no outcome.
+    pub fn of_broadcast_tx_async(result: &BroadcastTxAsyncResult) -> Self {
+        match result {
+            Ok(_) => Self::Succeeded,
+            Err(err) => Self::of_rpc_error(err),
+        }
+    }
+
+    /// Categorizes errors of any RPC method. Under load, requests failing at the transport level
+    /// have mostly timed out.
+    pub fn of_rpc_error<E>(err: &JsonRpcError<E>) -> Self {
+        match err {
+            JsonRpcError::TransportError(_) => Self::Timeout,
+            JsonRpcError::ServerError(_) => Self::OtherRpcError,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::NonceError => "nonce_error",
+            Self::Timeout => "timeout",
+            Self::OtherRpcError => "other_rpc_error",
+        }
+    }
+
+    pub(crate) fn idx(&self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|outcome| outcome == self)
+            .unwrap()
+    }
+}
+
+#[derive(Default)]
+pub struct LoadMetrics {
+    txs_sent: AtomicU64,
+    outstanding_requests: AtomicI64,
+    responses: [AtomicU64; ResponseOutcome::ALL.len()],
+    /// Non-cumulative counts per bucket of [`LATENCY_BUCKETS`], followed by the `+Inf` bucket.
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    latency_sum_micros: AtomicU64,
+}
+
+/// Values of [`LoadMetrics`] at one point in time. Comparing two snapshots yields rates.
+#[derive(Clone, Debug, Default)]
+pub struct LoadMetricsSnapshot {
+    pub txs_sent: u64,
+    pub outstanding_requests: i64,
+    pub(crate) responses: [u64; ResponseOutcome::ALL.len()],
+    pub(crate) latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+impl LoadMetricsSnapshot {
+    pub fn num_responses(&self, outcome: ResponseOutcome) -> u64 {
+        self.responses[outcome.idx()]
+    }
+
+    pub fn num_all_responses(&self) -> u64 {
+        self.responses.iter().sum()
+    }
+
+    pub fn num_errors(&self) -> u64 {
+        self.num_all_responses() - self.num_responses(ResponseOutcome::Succeeded)
+    }
+
+    /// Estimates the `quantile` of latencies of responses received since `earlier` as the upper
+    /// bound of the histogram bucket it falls into. `f64::INFINITY` stands for the `+Inf` bucket.
+    /// Returns `None` if there were no responses.
+    pub fn latency_quantile_since(&self, earlier: &Self, quantile: f64) -> Option<f64> {
+        let counts: Vec<u64> = self
+            .latency_buckets
+            .iter()
+            .zip(earlier.latency_buckets.iter())
+            .map(|(count, earlier_count)| count - earlier_count)
+            .collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = (quantile * total as f64).ceil().max(1.0) as u64;
+        let mut cumulative_count = 0;
+        for (idx, count) in counts.iter().enumerate() {
+            cumulative_count += count;
+            if cumulative_count >= rank {
+                return Some(LATENCY_BUCKETS.get(idx).copied().unwrap_or(f64::INFINITY));
+            }
+        }
+        Some(f64::INFINITY)
+    }
+}
+
+/// A request sent to the RPC. It counts as outstanding until dropped.
+pub struct InFlightRequest {
+    metrics: &'static LoadMetrics,
+    started: Instant,
+}
+
+impl InFlightRequest {
+    /// Records the outcome of the request and the time it took.
+    pub fn finish(self, outcome: ResponseOutcome) {
+        self.metrics
+            .record_response(outcome, self.started.elapsed());
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.metrics
+            .outstanding_requests
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LoadMetrics {
+    /// To be called right before a transaction is sent.
+    pub fn start_request(&'static self) -> InFlightRequest {
+        self.txs_sent.fetch_add(1, Ordering::Relaxed);
+        self.outstanding_requests.fetch_add(1, Ordering::Relaxed);
+        InFlightRequest {
+            metrics: self,
+            started: Instant::now(),
+        }
+    }
+
+    fn record_response(&self, outcome: ResponseOutcome, latency: Duration) {
+        self.responses[outcome.idx()].fetch_add(1, Ordering::Relaxed);
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|upper_bound| latency.as_secs_f64() <= *upper_bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_micros.fetch_add(
+            u64::try_from(latency.as_micros()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn snapshot(&self) -> LoadMetricsSnapshot {
+        LoadMetricsSnapshot {
+            txs_sent: self.txs_sent.load(Ordering::Relaxed),
+            outstanding_requests: self.outstanding_requests.load(Ordering::Relaxed),
+            responses: self
+                .responses
+                .each_ref()
+                .map(|count| count.load(Ordering::Relaxed)),
+            latency_buckets: self
+                .latency_buckets
+                .each_ref()
+                .map(|count| count.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "# HELP near_benchmarking_txs_sent_total Transactions sent."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE near_benchmarking_txs_sent_total counter").unwrap();
+        writeln!(
+            out,
+            "near_benchmarking_txs_sent_total {}",
+            self.txs_sent.load(Ordering::Relaxed)
+        )
+        .unwrap();
+
+        writeln!(
+            out,
+            "# HELP near_benchmarking_outstanding_requests Requests awaiting a response."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE near_benchmarking_outstanding_requests gauge").unwrap();
+        writeln!(
+            out,
+            "near_benchmarking_outstanding_requests {}",
+            self.outstanding_requests.load(Ordering::Relaxed)
+        )
+        .unwrap();
+
+        writeln!(
+            out,
+            "# HELP near_benchmarking_responses_total Responses to transactions by outcome."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE near_benchmarking_responses_total counter").unwrap();
+        for outcome in ResponseOutcome::ALL {
+            writeln!(
+                out,
+                "near_benchmarking_responses_total{{outcome=\"{}\"}} {}",
+                outcome.label(),
+                self.responses[outcome.idx()].load(Ordering::Relaxed)
+            )
+            .unwrap();
+        }
+
+        let name = "near_benchmarking_response_latency_seconds";
+        writeln!(
+            out,
+            "# HELP {name} Time from sending a transaction until its response."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE {name} histogram").unwrap();
+        let mut cumulative_count = 0;
+        for (idx, bucket) in self.latency_buckets.iter().enumerate() {
+            cumulative_count += bucket.load(Ordering::Relaxed);
+            let upper_bound = LATENCY_BUCKETS
+                .get(idx)
+                .map_or("+Inf".to_string(), |upper_bound| upper_bound.to_string());
+            writeln!(
+                out,
+                "{name}_bucket{{le=\"{upper_bound}\"}} {cumulative_count}"
+            )
+            .unwrap();
+        }
+        let latency_sum =
+            Duration::from_micros(self.latency_sum_micros.load(Ordering::Relaxed)).as_secs_f64();
+        writeln!(out, "{name}_sum {latency_sum}").unwrap();
+        writeln!(out, "{name}_count {cumulative_count}").unwrap();
+        out
+    }
+}
+
+/// Serves [`load_metrics`] on `http://<addr>/metrics` in the background, to be scraped by
+/// Prometheus.
+pub async fn serve_load_metrics(addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(
+        "Serving load metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(respond_to_scrape(stream));
+                }
+                Err(err) => warn!("failed to accept metrics connection: {err}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn respond_to_scrape(mut stream: TcpStream) {
+    let mut buf = [0; 4096];
+    let num_read = match stream.read(&mut buf).await {
+        Ok(num_read) => num_read,
+        Err(_) => return,
+    };
+    let response = if buf[..num_read].starts_with(b"GET /metrics") {
+        let body = load_metrics().render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    // The scraper might have gone away, which is not a concern of the benchmark.
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use near_jsonrpc_client::errors::{
+        JsonRpcServerError, JsonRpcTransportSendError, RpcTransportError,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_transport_errors_are_timeouts() {
+        let transport_error: RpcCallResult =
+            Err(JsonRpcError::TransportError(RpcTransportError::SendError(
+                JsonRpcTransportSendError::PayloadSerializeError(io::Error::other("timed out")),
+            )));
+        assert_eq!(
+            ResponseOutcome::of_send_tx(&transport_error),
+            ResponseOutcome::Timeout
+        );
+        let server_error: JsonRpcError<RpcTransactionError> = JsonRpcError::ServerError(
+            JsonRpcServerError::HandlerError(RpcTransactionError::TimeoutError),
+        );
+        assert_eq!(
+            ResponseOutcome::of_rpc_error(&server_error),
+            ResponseOutcome::OtherRpcError
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics: &'static LoadMetrics = Box::leak(Box::default());
+        metrics.start_request().finish(ResponseOutcome::Succeeded);
+        let outstanding = metrics.start_request();
+        metrics.record_response(ResponseOutcome::Timeout, Duration::from_secs(90));
+
+        let rendered = metrics.render();
+        for line in [
+            "near_benchmarking_txs_sent_total 2",
+            "near_benchmarking_outstanding_requests 1",
+            "near_benchmarking_responses_total{outcome=\"succeeded\"} 1",
+            "near_benchmarking_responses_total{outcome=\"timeout\"} 1",
+            "near_benchmarking_response_latency_seconds_bucket{le=\"60\"} 1",
+            "near_benchmarking_response_latency_seconds_bucket{le=\"+Inf\"} 2",
+            "near_benchmarking_response_latency_seconds_count 2",
+        ] {
+            assert!(rendered.contains(line), "missing {line} in:\n{rendered}");
+        }
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.num_errors(), 1);
+        assert_eq!(
+            snapshot.latency_quantile_since(&LoadMetricsSnapshot::default(), 0.5),
+            Some(0.01)
+        );
+        assert_eq!(
+            snapshot.latency_quantile_since(&LoadMetricsSnapshot::default(), 0.99),
+            Some(f64::INFINITY)
+        );
+        assert_eq!(snapshot.latency_quantile_since(&snapshot, 0.5), None);
+
+        drop(outstanding);
+        assert!(metrics
+            .render()
+            .contains("near_benchmarking_outstanding_requests 0"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use log::info;
+use near_crypto::Signer;
+use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+use near_primitives::{
+    action::{
+        delegate::{DelegateAction, NonDelegateAction, SignedDelegateAction},
+        Action,
+    },
+    errors::{ActionErrorKind, InvalidTxError, TxExecutionError},
+    serialize::dec_format,
+    types::{AccountId, Balance, BlockHeight, Nonce},
+    views::ExecutionStatusView,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    finality::FinalityTimings, latency::LatencySummary, rpc::is_transaction_and_receipts_success,
+    rpc_response_handler::RpcCallResult,
+};
+
+/// Creates a `DelegateAction` of `sender_id` and signs it with `signer`, which must hold an access
+/// key of `sender_id` with nonce below `nonce`.
+pub fn new_signed_delegate_action(
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    actions: Vec<Action>,
+    nonce: Nonce,
+    max_block_height: BlockHeight,
+    signer: &Signer,
+) -> anyhow::Result<SignedDelegateAction> {
+    let actions = actions
+        .into_iter()
+        .map(NonDelegateAction::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let delegate_action = DelegateAction {
+        sender_id,
+        receiver_id,
+        actions,
+        nonce,
+        max_block_height,
+        public_key: signer.public_key(),
+    };
+    let signature = signer.sign(delegate_action.get_nep461_hash().as_ref());
+    Ok(SignedDelegateAction {
+        delegate_action,
+        signature,
+    })
+}
+
+/// Response to a relayer transaction wrapping a `SignedDelegateAction`.
+pub struct MetaTxResult {
+    /// Index of the relayer that signed the transaction.
+    pub relayer_idx: usize,
+    pub result: RpcCallResult,
+    pub finality_timings: FinalityTimings,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MetaTxOutcome {
+    Succeeded,
+    /// The relayer transaction was rejected due to the nonce of the relayer's access key.
+    RelayerNonceError,
+    /// The delegate action failed due to the nonce of the user's access key.
+    DelegateNonceError,
+    OtherError,
+}
+
+fn classify(result: &RpcCallResult) -> MetaTxOutcome {
+    let response = match result {
+        Ok(response) => response,
+        Err(err) => {
+            return match err.handler_error() {
+                Some(RpcTransactionError::InvalidTransaction {
+                    context:
+                        InvalidTxError::InvalidNonce { .. } | InvalidTxError::NonceTooLarge { .. },
+                }) => MetaTxOutcome::RelayerNonceError,
+                _ => MetaTxOutcome::OtherError,
+            }
+        }
+    };
+    if is_transaction_and_receipts_success(response) {
+        return MetaTxOutcome::Succeeded;
+    }
+    let is_delegate_nonce_error = response
+        .final_execution_outcome
+        .clone()
+        .map(|outcome| outcome.into_outcome().receipts_outcome)
+        .unwrap_or_default()
+        .iter()
+        .any(|receipt_outcome| {
+            matches!(
+                &receipt_outcome.outcome.status,
+                ExecutionStatusView::Failure(TxExecutionError::ActionError(err))
+                    if matches!(
+                        err.kind,
+                        ActionErrorKind::DelegateActionInvalidNonce { .. }
+                            | ActionErrorKind::DelegateActionNonceTooLarge { .. }
+                    )
+            )
+        });
+    if is_delegate_nonce_error {
+        MetaTxOutcome::DelegateNonceError
+    } else {
+        MetaTxOutcome::OtherError
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RelayerStats {
+    pub account_id: AccountId,
+    pub num_sent: u64,
+    pub num_nonce_errors: u64,
+}
+
+/// Results of a meta transaction benchmark. Relayer nonce contention, i.e. rejections and queueing
+/// of transactions sharing few relayer keys, is reported separately from the cost of executing
+/// delegate actions.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetaTxReport {
+    pub num_sent: u64,
+    pub num_succeeded: u64,
+    pub num_relayer_nonce_errors: u64,
+    pub num_delegate_nonce_errors: u64,
+    pub num_other_errors: u64,
+    pub relayers: Vec<RelayerStats>,
+    /// Time until relayer transactions are included, which grows with contention for relayer
+    /// nonces.
+    pub inclusion_latency: LatencySummary,
+    /// Time from inclusion until delegate actions and their inner actions are executed.
+    pub execution_latency: LatencySummary,
+    /// Gas burnt converting relayer transactions into receipts.
+    #[serde(with = "dec_format")]
+    pub tx_gas_burnt: u128,
+    /// Gas burnt executing delegate actions and their inner actions.
+    #[serde(with = "dec_format")]
+    pub receipts_gas_burnt: u128,
+    #[serde(with = "dec_format")]
+    pub tokens_burnt: Balance,
+}
+
+impl MetaTxReport {
+    pub fn new(relayer_ids: &[AccountId], results: &[MetaTxResult]) -> Self {
+        let mut report = Self {
+            num_sent: u64::try_from(results.len()).unwrap(),
+            num_succeeded: 0,
+            num_relayer_nonce_errors: 0,
+            num_delegate_nonce_errors: 0,
+            num_other_errors: 0,
+            relayers: relayer_ids
+                .iter()
+                .map(|account_id| RelayerStats {
+                    account_id: account_id.clone(),
+                    num_sent: 0,
+                    num_nonce_errors: 0,
+                })
+                .collect(),
+            inclusion_latency: LatencySummary::default(),
+            execution_latency: LatencySummary::default(),
+            tx_gas_burnt: 0,
+            receipts_gas_burnt: 0,
+            tokens_burnt: 0,
+        };
+
+        let mut inclusion_latencies = vec![];
+        let mut execution_latencies: Vec<Duration> = vec![];
+        for result in results {
+            let relayer = &mut report.relayers[result.relayer_idx];
+            relayer.num_sent += 1;
+            match classify(&result.result) {
+                MetaTxOutcome::Succeeded => report.num_succeeded += 1,
+                MetaTxOutcome::RelayerNonceError => {
+                    report.num_relayer_nonce_errors += 1;
+                    relayer.num_nonce_errors += 1;
+                }
+                MetaTxOutcome::DelegateNonceError => report.num_delegate_nonce_errors += 1,
+                MetaTxOutcome::OtherError => report.num_other_errors += 1,
+            }
+
+            let timings = &result.finality_timings;
+            inclusion_latencies.extend(timings.included);
+            if let (Some(included), Some(executed)) =
+                (timings.included, timings.executed_optimistic)
+            {
+                execution_latencies.push(executed.saturating_sub(included));
+            }
+
+            let outcome = match &result.result {
+                Ok(response) => match &response.final_execution_outcome {
+                    Some(outcome) => outcome.clone().into_outcome(),
+                    None => continue,
+                },
+                Err(_) => continue,
+            };
+            report.tx_gas_burnt += u128::from(outcome.transaction_outcome.outcome.gas_burnt);
+            report.tokens_burnt += outcome.transaction_outcome.outcome.tokens_burnt;
+            for receipt_outcome in outcome.receipts_outcome.iter() {
+                report.receipts_gas_burnt += u128::from(receipt_outcome.outcome.gas_burnt);
+                report.tokens_burnt += receipt_outcome.outcome.tokens_burnt;
+            }
+        }
+        report.inclusion_latency = LatencySummary::from_durations(&inclusion_latencies);
+        report.execution_latency = LatencySummary::from_durations(&execution_latencies);
+        report
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Meta txs: {} sent, {} succeeded, {} relayer nonce errors, {} delegate nonce errors, {} other errors",
+            self.num_sent,
+            self.num_succeeded,
+            self.num_relayer_nonce_errors,
+            self.num_delegate_nonce_errors,
+            self.num_other_errors
+        );
+        info!("Relayer tx inclusion latency: {}", self.inclusion_latency);
+        info!("Delegate execution latency: {}", self.execution_latency);
+        info!(
+            "Gas burnt: {} by relayer txs, {} by receipts; tokens burnt: {}",
+            self.tx_gas_burnt, self.receipts_gas_burnt, self.tokens_burnt
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+    use near_primitives::{action::TransferAction, hash::CryptoHash};
+
+    use super::*;
+
+    #[test]
+    fn test_new_signed_delegate_action_verifies() {
+        let sender_id: AccountId = "user_0.test.near".parse().unwrap();
+        let signer = Signer::from(InMemorySigner::from_random(
+            sender_id.clone(),
+            KeyType::ED25519,
+        ));
+        let signed = new_signed_delegate_action(
+            sender_id,
+            "user_1.test.near".parse().unwrap(),
+            vec![Action::Transfer(TransferAction { deposit: 1 })],
+            1,
+            100,
+            &signer,
+        )
+        .unwrap();
+        assert!(signed.verify());
+    }
+
+    #[test]
+    fn test_report_counts_relayer_nonce_errors() {
+        let relayer_ids: Vec<AccountId> = vec![
+            "relayer_0.test.near".parse().unwrap(),
+            "relayer_1.test.near".parse().unwrap(),
+        ];
+        let nonce_error = || MetaTxResult {
+            relayer_idx: 1,
+            result: Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::InvalidTransaction {
+                    context: InvalidTxError::InvalidNonce {
+                        tx_nonce: 5,
+                        ak_nonce: 5,
+                    },
+                },
+            ))),
+            finality_timings: FinalityTimings {
+                tx_hash: CryptoHash::default(),
+                included: None,
+                executed_optimistic: None,
+                final_: None,
+            },
+        };
+        let timeout = MetaTxResult {
+            relayer_idx: 0,
+            result: Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::TimeoutError,
+            ))),
+            ..nonce_error()
+        };
+
+        let report = MetaTxReport::new(&relayer_ids, &[nonce_error(), nonce_error(), timeout]);
+        assert_eq!(report.num_sent, 3);
+        assert_eq!(report.num_relayer_nonce_errors, 2);
+        assert_eq!(report.num_other_errors, 1);
+        assert_eq!(report.relayers[0].num_sent, 1);
+        assert_eq!(report.relayers[0].num_nonce_errors, 0);
+        assert_eq!(report.relayers[1].num_nonce_errors, 2);
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Args;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle, time};
+
+/// Metric families of `neard` recorded by default: transaction pool size, chunk production time,
+/// block processing time, applied receipts and the delayed receipts queue.
+const DEFAULT_NODE_METRICS: &str = "near_transaction_pool_entries,near_produce_chunk_time,near_block_processing_time,near_incoming_receipt_processed_total,near_delayed_receipts_count";
+
+#[derive(Args, Debug)]
+pub struct NodeMetricsArgs {
+    /// Prometheus endpoint of the node, e.g. `http://localhost:3030/metrics`. If provided, it is
+    /// scraped during the run and the selected series are written to the report.
+    #[arg(long)]
+    pub node_metrics_url: Option<String>,
+    /// Interval (in milliseconds) at which the node's metrics are scraped.
+    #[arg(long, default_value_t = 1000)]
+    pub node_metrics_interval_millis: u64,
+    /// Comma separated metric families to record. Besides series named like the family, its
+    /// `_sum`, `_count` and `_total` series are recorded. Histogram buckets are skipped.
+    #[arg(long, value_delimiter = ',', default_value = DEFAULT_NODE_METRICS)]
+    pub node_metrics_families: Vec<String>,
+}
+
+/// Values of the selected series at one point in time.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeMetricsSample {
+    /// Milliseconds since the unix epoch, to line up samples with other dashboards.
+    pub timestamp_ms: u64,
+    /// Maps series, i.e. metric name and labels like `near_delayed_receipts_count{shard_id="0"}`,
+    /// to their value.
+    pub values: BTreeMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct NodeMetricsReport {
+    pub url: String,
+    pub num_failed_scrapes: u64,
+    pub samples: Vec<NodeMetricsSample>,
+}
+
+impl NodeMetricsReport {
+    pub fn log_summary(&self) {
+        let num_series = self
+            .samples
+            .last()
+            .map(|sample| sample.values.len())
+            .unwrap_or_default();
+        info!(
+            "Node metrics: {} samples of {} series scraped from {}, {} scrapes failed",
+            self.samples.len(),
+            num_series,
+            self.url,
+            self.num_failed_scrapes
+        );
+    }
+}
+
+/// Periodically scrapes the Prometheus metrics of a node.
+pub struct NodeMetricsCollector {
+    http_client: reqwest::Client,
+    url: String,
+    interval: Duration,
+    families: Vec<String>,
+}
+
+/// A collector running in the background, see [`NodeMetricsCollector::spawn`].
+pub struct NodeMetricsHandle {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<NodeMetricsReport>,
+}
+
+impl NodeMetricsHandle {
+    /// Scrapes the metrics one last time and returns all samples.
+    pub async fn stop(self) -> NodeMetricsReport {
+        // Sending fails only if the task finished, which it does only after receiving.
+        let _ = self.stop.send(());
+        self.task
+            .await
+            .expect("node metrics collector should succeed")
+    }
+}
+
+impl NodeMetricsCollector {
+    /// Returns `None` if no url is provided.
+    pub fn from_args(args: &NodeMetricsArgs) -> anyhow::Result<Option<Self>> {
+        let url = match &args.node_metrics_url {
+            Some(url) => url.clone(),
+            None => return Ok(None),
+        };
+        let interval = Duration::from_millis(args.node_metrics_interval_millis);
+        let http_client = reqwest::Client::builder()
+            .timeout(interval.max(Duration::from_secs(1)))
+            .build()?;
+        Ok(Some(Self {
+            http_client,
+            url,
+            interval,
+            families: args.node_metrics_families.clone(),
+        }))
+    }
+
+    pub fn spawn(self) -> NodeMetricsHandle {
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(self.run(stop_rx));
+        NodeMetricsHandle { stop, task }
+    }
+
+    async fn run(self, mut stop: oneshot::Receiver<()>) -> NodeMetricsReport {
+        let mut report = NodeMetricsReport {
+            url: self.url.clone(),
+            ..Default::default()
+        };
+        let mut interval = time::interval(self.interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            let stopped = tokio::select! {
+                _ = &mut stop => true,
+                _ = interval.tick() => false,
+            };
+            match self.scrape().await {
+                Ok(sample) => report.samples.push(sample),
+                Err(err) => {
+                    warn!("failed to scrape node metrics from {}: {err}", self.url);
+                    report.num_failed_scrapes += 1;
+                }
+            }
+            if stopped {
+                return report;
+            }
+        }
+    }
+
+    async fn scrape(&self) -> anyhow::Result<NodeMetricsSample> {
+        let body = self
+            .http_client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the unix epoch")
+            .as_millis();
+        Ok(NodeMetricsSample {
+            timestamp_ms: u64::try_from(timestamp_ms).unwrap(),
+            values: parse_metrics(&body, &self.families),
+        })
+    }
+}
+
+/// Extracts the series of `families` from metrics in the Prometheus text format.
+fn parse_metrics(text: &str, families: &[String]) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Label values may contain spaces, hence the series ends with the closing brace, if any.
+        let series_end = match (line.find('{'), line.rfind('}')) {
+            (Some(_), Some(end)) => end + 1,
+            _ => line.find(char::is_whitespace).unwrap_or(line.len()),
+        };
+        let (series, rest) = line.split_at(series_end);
+        let name = series.split('{').next().unwrap_or_default();
+        if !families.iter().any(|family| is_of_family(name, family)) {
+            continue;
+        }
+        // A timestamp may follow the value.
+        let value = rest.split_whitespace().next().map(str::parse::<f64>);
+        if let Some(Ok(value)) = value {
+            // Non-finite values like `NaN` cannot be represented in json.
+            if value.is_finite() {
+                values.insert(series.to_string(), value);
+            }
+        }
+    }
+    values
+}
+
+fn is_of_family(name: &str, family: &str) -> bool {
+    match name.strip_prefix(family) {
+        Some(suffix) => matches!(suffix, "" | "_sum" | "_count" | "_total"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::serve_http_stand_in;
+
+    use super::*;
+
+    const METRICS: &str = r#"# HELP near_block_processing_time Time taken to process blocks
+# TYPE near_block_processing_time histogram
+near_block_processing_time_bucket{le="0.005"} 3
+near_block_processing_time_bucket{le="+Inf"} 10
+near_block_processing_time_sum 1.5
+near_block_processing_time_count 10
+near_delayed_receipts_count{shard_id="0"} 4
+near_delayed_receipts_count{shard_id="1"} 0 1700000000000
+near_transaction_pool_entries 120
+near_transaction_pool_entries_by_shard{shard_id="0"} 120
+near_other_metric 7
+"#;
+
+    fn families() -> Vec<String> {
+        [
+            "near_block_processing_time",
+            "near_delayed_receipts_count",
+            "near_transaction_pool_entries",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    #[test]
+    fn test_parse_metrics() {
+        let values = parse_metrics(METRICS, &families());
+        let expected: BTreeMap<String, f64> = [
+            ("near_block_processing_time_count", 10.0),
+            ("near_block_processing_time_sum", 1.5),
+            ("near_delayed_receipts_count{shard_id=\"0\"}", 4.0),
+            ("near_delayed_receipts_count{shard_id=\"1\"}", 0.0),
+            ("near_transaction_pool_entries", 120.0),
+        ]
+        .into_iter()
+        .map(|(series, value)| (series.to_string(), value))
+        .collect();
+        assert_eq!(values, expected);
+    }
+
+    #[tokio::test]
+    async fn test_collector_scrapes_stand_in() -> anyhow::Result<()> {
+        let url = serve_http_stand_in(METRICS.to_string()).await?;
+        let args = NodeMetricsArgs {
+            node_metrics_url: Some(url.clone()),
+            node_metrics_interval_millis: 10,
+            node_metrics_families: families(),
+        };
+        let handle = NodeMetricsCollector::from_args(&args)?.unwrap().spawn();
+        time::sleep(Duration::from_millis(50)).await;
+        let report = handle.stop().await;
+
+        assert_eq!(report.url, url);
+        assert_eq!(report.num_failed_scrapes, 0);
+        assert!(report.samples.len() >= 2);
+        assert_eq!(
+            report.samples[0].values["near_transaction_pool_entries"],
+            120.0
+        );
+        Ok(())
+    }
+}
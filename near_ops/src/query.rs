@@ -0,0 +1,386 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
+
+use clap::{Args, ValueEnum};
+use log::{info, warn};
+use near_jsonrpc_client::{
+    errors::JsonRpcError,
+    methods::{block::RpcBlockRequest, query::RpcQueryRequest},
+};
+use near_primitives::{
+    types::{AccountId, BlockReference},
+    views::QueryRequest,
+};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    account::Account, contract::ArgsTemplate, latency::LatencySummary,
+    load_metrics::ResponseOutcome, rpc_client::RpcClient,
+};
+
+/// Kinds of read requests sent by the queries benchmark.
+#[derive(ValueEnum, Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueryKind {
+    ViewAccount,
+    ViewAccessKey,
+    /// Calls a view method of a contract, e.g. `ft_balance_of`.
+    CallFunction,
+    Block,
+}
+
+impl fmt::Display for QueryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// Weights of query kinds, parsed from e.g. `view-account=80,block=20`.
+#[derive(Clone, Debug)]
+pub struct QueryMix {
+    kinds: Vec<QueryKind>,
+    weights: Vec<u32>,
+    distribution: WeightedIndex<u32>,
+}
+
+impl QueryMix {
+    pub fn new(weights: Vec<(QueryKind, u32)>) -> anyhow::Result<Self> {
+        let (kinds, weights): (Vec<_>, Vec<_>) = weights.into_iter().unzip();
+        let distribution = WeightedIndex::new(&weights)
+            .map_err(|err| anyhow::anyhow!("invalid query weights: {err}"))?;
+        Ok(Self {
+            kinds,
+            weights,
+            distribution,
+        })
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> QueryKind {
+        self.kinds[self.distribution.sample(rng)]
+    }
+
+    /// Whether queries of `kind` are sampled.
+    pub fn contains(&self, kind: QueryKind) -> bool {
+        self.kinds
+            .iter()
+            .zip(self.weights.iter())
+            .any(|(k, weight)| *k == kind && *weight > 0)
+    }
+
+    pub fn weights(&self) -> BTreeMap<QueryKind, u32> {
+        self.kinds
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+            .collect()
+    }
+}
+
+impl FromStr for QueryMix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = vec![];
+        for entry in s.split(',') {
+            let (kind, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("expected <kind>=<weight>, got {entry}"))?;
+            let kind =
+                QueryKind::from_str(kind.trim(), true).map_err(|err| anyhow::anyhow!(err))?;
+            weights.push((kind, weight.trim().parse()?));
+        }
+        Self::new(weights)
+    }
+}
+
+/// Parses a [`QueryMix`], see its [`FromStr`] implementation.
+pub fn parse_query_mix(s: &str) -> anyhow::Result<QueryMix> {
+    s.parse()
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    /// Comma separated weights of query kinds, e.g.
+    /// `view-account=50,view-access-key=30,call-function=15,block=5`. For each request a kind is
+    /// sampled according to the weights.
+    #[arg(long, value_parser = parse_query_mix, default_value = "view-account=1")]
+    pub query_mix: QueryMix,
+    /// Contract whose view method is called by `call-function` queries.
+    #[arg(long)]
+    pub call_function_contract_id: Option<AccountId>,
+    #[arg(long, default_value = "ft_balance_of")]
+    pub call_function_method_name: String,
+    /// Template of view method arguments, see `benchmark-function-calls --args`. `{sender}` is
+    /// the queried account.
+    #[arg(long, default_value = r#"{"account_id": "{sender}"}"#)]
+    pub call_function_args: String,
+}
+
+/// A read request created by [`QueryGenerator::new_query`].
+pub struct Query {
+    pub kind: QueryKind,
+    request: QueryRequestKind,
+}
+
+enum QueryRequestKind {
+    Query(RpcQueryRequest),
+    Block(RpcBlockRequest),
+}
+
+impl Query {
+    /// Sends the request, discarding the response. Views are made at the latest block. Returns the
+    /// outcome recorded in the load metrics and, if the request failed, the error message.
+    pub async fn send(self, client: &RpcClient) -> (ResponseOutcome, Option<String>) {
+        match self.request {
+            QueryRequestKind::Query(request) => outcome_of(client.call(request).await),
+            QueryRequestKind::Block(request) => outcome_of(client.call(request).await),
+        }
+    }
+}
+
+fn outcome_of<R, E: fmt::Display>(
+    result: Result<R, JsonRpcError<E>>,
+) -> (ResponseOutcome, Option<String>) {
+    match result {
+        Ok(_) => (ResponseOutcome::Succeeded, None),
+        Err(err) => (ResponseOutcome::of_rpc_error(&err), Some(err.to_string())),
+    }
+}
+
+/// Creates requests according to a [`QueryMix`].
+pub struct QueryGenerator {
+    mix: QueryMix,
+    call_function_contract_id: Option<AccountId>,
+    call_function_method_name: String,
+    call_function_args: ArgsTemplate,
+}
+
+impl QueryGenerator {
+    pub fn new(args: &QueryArgs) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !args.query_mix.contains(QueryKind::CallFunction)
+                || args.call_function_contract_id.is_some(),
+            "call-function requires --call-function-contract-id"
+        );
+        Ok(Self {
+            mix: args.query_mix.clone(),
+            call_function_contract_id: args.call_function_contract_id.clone(),
+            call_function_method_name: args.call_function_method_name.clone(),
+            call_function_args: ArgsTemplate::new(args.call_function_args.clone())?,
+        })
+    }
+
+    pub fn mix(&self) -> &QueryMix {
+        &self.mix
+    }
+
+    /// Samples a query kind and creates a request of that kind about `account`. Access keys are
+    /// chosen at random among the keys of `account`.
+    pub fn new_query(&self, account: &Account, i: u64, rng: &mut impl Rng) -> Query {
+        let kind = self.mix.sample(rng);
+        let block_reference = BlockReference::latest();
+        let query = |request| {
+            QueryRequestKind::Query(RpcQueryRequest {
+                block_reference: block_reference.clone(),
+                request,
+            })
+        };
+        let request = match kind {
+            QueryKind::ViewAccount => query(QueryRequest::ViewAccount {
+                account_id: account.id.clone(),
+            }),
+            QueryKind::ViewAccessKey => {
+                let key_idx = rng.gen_range(0..account.num_keys());
+                query(QueryRequest::ViewAccessKey {
+                    account_id: account.id.clone(),
+                    public_key: account.public_keys().nth(key_idx).unwrap().clone(),
+                })
+            }
+            QueryKind::CallFunction => {
+                let contract_id = self.call_function_contract_id.clone().unwrap();
+                let args = self
+                    .call_function_args
+                    .render(&account.id, &contract_id, i, rng);
+                query(QueryRequest::CallFunction {
+                    account_id: contract_id,
+                    method_name: self.call_function_method_name.clone(),
+                    args: args.into_bytes().into(),
+                })
+            }
+            QueryKind::Block => QueryRequestKind::Block(RpcBlockRequest { block_reference }),
+        };
+        Query { kind, request }
+    }
+}
+
+/// Outcome of a sent [`Query`].
+pub struct QueryResult {
+    pub kind: QueryKind,
+    pub latency: Duration,
+    /// The error message if the request failed.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct QueryKindReport {
+    pub num_sent: u64,
+    pub num_errors: u64,
+    /// Message of the first error, to tell why requests of this kind fail without logging each.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<String>,
+    /// Latency of successful requests.
+    pub latency: LatencySummary,
+}
+
+/// Composition and latency of the requests sent by the queries benchmark.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct QueryReport {
+    pub num_sent: u64,
+    pub num_errors: u64,
+    pub weights: BTreeMap<QueryKind, u32>,
+    pub per_kind: BTreeMap<QueryKind, QueryKindReport>,
+}
+
+impl QueryReport {
+    pub fn new(mix: &QueryMix, results: &[QueryResult]) -> Self {
+        let mut per_kind: BTreeMap<QueryKind, QueryKindReport> = BTreeMap::new();
+        let mut latencies: BTreeMap<QueryKind, Vec<Duration>> = BTreeMap::new();
+        for result in results {
+            let report = per_kind.entry(result.kind).or_default();
+            report.num_sent += 1;
+            match &result.error {
+                None => latencies
+                    .entry(result.kind)
+                    .or_default()
+                    .push(result.latency),
+                Some(error) => {
+                    report.num_errors += 1;
+                    report.first_error.get_or_insert_with(|| error.clone());
+                }
+            }
+        }
+        for (kind, latencies) in latencies {
+            per_kind.get_mut(&kind).unwrap().latency = LatencySummary::from_durations(&latencies);
+        }
+        Self {
+            num_sent: per_kind.values().map(|report| report.num_sent).sum(),
+            num_errors: per_kind.values().map(|report| report.num_errors).sum(),
+            weights: mix.weights(),
+            per_kind,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Queries: {} sent, {} errors",
+            self.num_sent, self.num_errors
+        );
+        for (kind, report) in self.per_kind.iter() {
+            info!(
+                "{kind}: {} sent, {} errors, latency {}",
+                report.num_sent, report.num_errors, report.latency
+            );
+            if let Some(error) = &report.first_error {
+                warn!("{kind} queries failed, first error: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, SecretKey};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_query_mix() {
+        let mix: QueryMix = "view-account=3, Block=1,call-function=0".parse().unwrap();
+        assert!(mix.contains(QueryKind::Block));
+        assert!(!mix.contains(QueryKind::CallFunction));
+        assert!(!mix.contains(QueryKind::ViewAccessKey));
+        assert_eq!(mix.weights()[&QueryKind::ViewAccount], 3);
+
+        assert!("view-account".parse::<QueryMix>().is_err());
+        assert!("view-state=1".parse::<QueryMix>().is_err());
+        assert!("block=0".parse::<QueryMix>().is_err());
+    }
+
+    #[test]
+    fn test_new_query() {
+        let args = QueryArgs {
+            query_mix: "call-function=1".parse().unwrap(),
+            call_function_contract_id: Some("ft.test.near".parse().unwrap()),
+            call_function_method_name: "ft_balance_of".to_string(),
+            call_function_args: r#"{"account_id": "{sender}"}"#.to_string(),
+        };
+        let generator = QueryGenerator::new(&args).unwrap();
+        let account = Account::new(
+            "user_0.test.near".parse().unwrap(),
+            SecretKey::from_random(KeyType::ED25519),
+            0,
+        );
+        let query = generator.new_query(&account, 0, &mut rand::thread_rng());
+        assert_eq!(query.kind, QueryKind::CallFunction);
+        match query.request {
+            QueryRequestKind::Query(RpcQueryRequest {
+                request:
+                    QueryRequest::CallFunction {
+                        account_id,
+                        method_name,
+                        args,
+                    },
+                ..
+            }) => {
+                assert_eq!(account_id.as_str(), "ft.test.near");
+                assert_eq!(method_name, "ft_balance_of");
+                assert_eq!(
+                    String::from_utf8(args.to_vec()).unwrap(),
+                    r#"{"account_id": "user_0.test.near"}"#
+                );
+            }
+            _ => panic!("expected a call function query"),
+        }
+
+        let args = QueryArgs {
+            call_function_contract_id: None,
+            ..args
+        };
+        assert!(QueryGenerator::new(&args).is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let mix: QueryMix = "view-account=1,block=1".parse().unwrap();
+        let result = |kind, latency_ms, is_ok: bool| QueryResult {
+            kind,
+            latency: Duration::from_millis(latency_ms),
+            error: (!is_ok).then(|| format!("error after {latency_ms} ms")),
+        };
+        let report = QueryReport::new(
+            &mix,
+            &[
+                result(QueryKind::ViewAccount, 10, true),
+                result(QueryKind::ViewAccount, 30, true),
+                result(QueryKind::ViewAccount, 1000, false),
+                result(QueryKind::Block, 5, true),
+            ],
+        );
+        let view_account = &report.per_kind[&QueryKind::ViewAccount];
+        assert_eq!(view_account.num_sent, 3);
+        assert_eq!(view_account.num_errors, 1);
+        assert_eq!(
+            view_account.first_error.as_deref(),
+            Some("error after 1000 ms")
+        );
+        assert_eq!(view_account.latency.count, 2);
+        assert_eq!(view_account.latency.max, Duration::from_millis(30));
+        assert_eq!(report.per_kind[&QueryKind::Block].num_sent, 1);
+        assert_eq!(report.num_sent, 4);
+        assert_eq!(report.num_errors, 1);
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fs, path::Path, time::Duration};
+
+use near_primitives::views::TxExecutionStatus;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_scan::BlockScanReport, deploy::DeployReport, finality::FinalityLatencyReport,
+    gas::GasReport, meta_tx::MetaTxReport, node_metrics::NodeMetricsReport, query::QueryReport,
+    rpc_client::ConnectionStats, throughput::ThroughputSample, tx_lifecycle::TxLifecycleReport,
+    tx_status_tracker::TxStatusReport, verification::VerificationReport, workload::WorkloadReport,
+};
+
+/// Results of a benchmark run, written as json to allow comparing and post-processing runs.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BenchmarkReport {
+    /// `None` for benchmarks that send no transactions, e.g. `benchmark-queries`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_txs_sent: Option<u64>,
+    #[serde(rename = "send_duration_ms", with = "duration_ms")]
+    pub send_duration: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_until: Option<TxExecutionStatus>,
+    pub connection_stats: ConnectionStats,
+    /// Seed of the random number generator, to reproduce the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Distribution of senders, see [`AccountDistribution`](crate::distribution::AccountDistribution).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_distribution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver_distribution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality_latency: Option<FinalityLatencyReport>,
+    /// Latencies and outcomes polled for transactions sent via `broadcast_tx_async`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_status: Option<TxStatusReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_lifecycle: Option<TxLifecycleReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_tx: Option<MetaTxReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workload: Option<WorkloadReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy: Option<DeployReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queries: Option<QueryReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_metrics: Option<NodeMetricsReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_scan: Option<BlockScanReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas: Option<GasReport>,
+    /// Per-second counts of the run, to spot throughput collapses and recoveries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<Vec<ThroughputSample>>,
+}
+
+impl BenchmarkReport {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let report = serde_json::from_str(&content)?;
+        Ok(report)
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// (De)serializes a [`Duration`] as floating point milliseconds, which is easier to consume by
+/// other tools than the default representation.
+pub mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let millis = f64::deserialize(deserializer)?;
+        Ok(Duration::from_secs_f64(millis / 1000.0))
+    }
+
+    pub mod option {
+        use std::time::Duration;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match duration {
+                Some(duration) => super::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            let millis = Option::<f64>::deserialize(deserializer)?;
+            Ok(millis.map(|millis| Duration::from_secs_f64(millis / 1000.0)))
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use clap::Args;
+use log::info;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Args, Debug)]
+pub struct SeedArgs {
+    /// Seed for all random choices, e.g. of receivers or queried accounts. Runs with the same seed
+    /// and accounts make the same choices, see `seeded_rng`. If not provided, a random seed is
+    /// used, which is logged and written to the report.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl SeedArgs {
+    /// Returns [`seeded_rng`] for the seed passed and logs the seed that was used.
+    pub fn rng(&self) -> (StdRng, u64) {
+        let (rng, seed) = seeded_rng(self.seed);
+        info!("Using seed {seed}");
+        (rng, seed)
+    }
+}
+
+/// Returns a random number generator seeded with `seed`, or with a random seed if `seed` is
+/// `None`, along with the seed that was used. Runs with the same seed and accounts make the same
+/// random choices, e.g. of receivers, transaction kinds and generated keys, which allows comparing
+/// equivalent workloads. Transactions still differ in block hashes and nonces.
+pub fn seeded_rng(seed: Option<u64>) -> (StdRng, u64) {
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    (StdRng::seed_from_u64(seed), seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let (mut rng_a, seed) = seeded_rng(None);
+        let (mut rng_b, seed_b) = seeded_rng(Some(seed));
+        assert_eq!(seed, seed_b);
+        let samples_a: Vec<u64> = (0..10).map(|_| rng_a.gen()).collect();
+        let samples_b: Vec<u64> = (0..10).map(|_| rng_b.gen()).collect();
+        assert_eq!(samples_a, samples_b);
+    }
+}
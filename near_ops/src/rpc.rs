@@ -1,24 +1,28 @@
-use std::{collections::HashMap, sync::LazyLock};
-
-use log::warn;
+use log::{debug, warn};
 use near_crypto::{InMemorySigner, PublicKey, Signer};
-use near_jsonrpc_client::{
-    methods::{
-        block::RpcBlockRequest, query::RpcQueryRequest, send_tx::RpcSendTransactionRequest,
-        tx::RpcTransactionResponse,
-    },
-    JsonRpcClient,
+use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+use near_jsonrpc_client::methods::{
+    block::RpcBlockRequest,
+    chunk::{ChunkReference, RpcChunkRequest},
+    query::RpcQueryRequest,
+    send_tx::RpcSendTransactionRequest,
+    tx::RpcTransactionResponse,
+    EXPERIMENTAL_protocol_config::{RpcProtocolConfigRequest, RpcProtocolConfigResponse},
 };
-use near_jsonrpc_primitives::types::query::QueryResponseKind;
+use near_jsonrpc_primitives::types::{blocks::RpcBlockError, query::QueryResponseKind};
 use near_primitives::{
+    hash::CryptoHash,
     transaction::Transaction,
-    types::{AccountId, BlockReference, Finality},
+    types::{AccountId, BlockHeight, BlockId, BlockReference, Finality},
     views::{
-        AccessKeyView, BlockView, ExecutionStatusView, FinalExecutionOutcomeView,
+        AccessKeyView, AccountView, BlockView, ChunkView, ExecutionStatusView,
         FinalExecutionStatus, QueryRequest, TxExecutionStatus,
     },
 };
 
+use tokio::task::JoinSet;
+
+use crate::rpc_client::RpcClient;
 use crate::rpc_response_handler::ResponseCheckSeverity;
 
 pub fn new_request(
@@ -32,14 +36,11 @@ pub fn new_request(
     }
 }
 
-pub async fn get_latest_block(client: &JsonRpcClient) -> anyhow::Result<BlockView> {
+pub async fn get_latest_block(client: &RpcClient) -> anyhow::Result<BlockView> {
     get_block(client, BlockReference::Finality(Finality::Final)).await
 }
 
-pub async fn get_block(
-    client: &JsonRpcClient,
-    block_ref: BlockReference,
-) -> anyhow::Result<BlockView> {
+pub async fn get_block(client: &RpcClient, block_ref: BlockReference) -> anyhow::Result<BlockView> {
     let request = RpcBlockRequest {
         block_reference: block_ref,
     };
@@ -47,8 +48,90 @@ pub async fn get_block(
     Ok(block_view)
 }
 
+/// Whether `err`, returned by [`get_block`], means no block exists at the requested height. Other
+/// errors, e.g. transport errors, may be temporary.
+pub fn is_unknown_block(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<JsonRpcError<RpcBlockError>>(),
+        Some(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+            RpcBlockError::UnknownBlock { .. }
+        )))
+    )
+}
+
+pub async fn get_chunk(client: &RpcClient, chunk_hash: CryptoHash) -> anyhow::Result<ChunkView> {
+    let request = RpcChunkRequest {
+        chunk_reference: ChunkReference::ChunkHash {
+            chunk_id: chunk_hash,
+        },
+    };
+    let chunk_view = client.call(request).await?;
+    Ok(chunk_view)
+}
+
+/// Fetches the chunks that are new in `block` concurrently. Fails if any of them cannot be
+/// fetched.
+pub async fn get_new_chunks(
+    client: &RpcClient,
+    block: &BlockView,
+) -> anyhow::Result<Vec<ChunkView>> {
+    let mut chunk_requests = JoinSet::new();
+    for chunk_header in block.chunks.iter() {
+        // Otherwise the chunk is missing and the header refers to a previous chunk.
+        if chunk_header.height_included == block.header.height {
+            let client = client.clone();
+            let chunk_hash = chunk_header.chunk_hash;
+            chunk_requests.spawn(async move { get_chunk(&client, chunk_hash).await });
+        }
+    }
+    let mut chunks = vec![];
+    while let Some(chunk) = chunk_requests.join_next().await {
+        chunks.push(chunk.expect("join should succeed")?);
+    }
+    Ok(chunks)
+}
+
+/// Passes the blocks above `last_scanned_height` up to and including `latest` to `scan` in
+/// ascending order, along with their new chunks. Heights without a block are skipped.
+///
+/// A block is passed on only once all its chunks are fetched, and `last_scanned_height` is advanced
+/// after each height. So on error, the next call resumes with the first height not scanned.
+pub async fn scan_blocks_up_to(
+    client: &RpcClient,
+    last_scanned_height: &mut BlockHeight,
+    latest: BlockView,
+    mut scan: impl FnMut(BlockView, Vec<ChunkView>),
+) -> anyhow::Result<()> {
+    let latest_height = latest.header.height;
+    for height in *last_scanned_height + 1..latest_height {
+        match get_block(client, BlockReference::BlockId(BlockId::Height(height))).await {
+            Ok(block) => {
+                let chunks = get_new_chunks(client, &block).await?;
+                scan(block, chunks);
+            }
+            Err(err) if is_unknown_block(&err) => debug!("no block at height {height}"),
+            Err(err) => return Err(err),
+        }
+        *last_scanned_height = height;
+    }
+    if latest_height > *last_scanned_height {
+        let chunks = get_new_chunks(client, &latest).await?;
+        scan(latest, chunks);
+        *last_scanned_height = latest_height;
+    }
+    Ok(())
+}
+
+pub async fn get_protocol_config(client: &RpcClient) -> anyhow::Result<RpcProtocolConfigResponse> {
+    let request = RpcProtocolConfigRequest {
+        block_reference: BlockReference::Finality(Finality::Final),
+    };
+    let protocol_config = client.call(request).await?;
+    Ok(protocol_config)
+}
+
 pub async fn view_access_key(
-    client: &JsonRpcClient,
+    client: &RpcClient,
     account_id: AccountId,
     public_key: PublicKey,
 ) -> anyhow::Result<AccessKeyView> {
@@ -66,6 +149,21 @@ pub async fn view_access_key(
     }
 }
 
+pub async fn view_account(
+    client: &RpcClient,
+    account_id: AccountId,
+) -> anyhow::Result<AccountView> {
+    let request = RpcQueryRequest {
+        block_reference: BlockReference::latest(),
+        request: QueryRequest::ViewAccount { account_id },
+    };
+    let response = client.call(request).await?;
+    match response.kind {
+        QueryResponseKind::ViewAccount(account_view) => Ok(account_view),
+        _ => Err(anyhow::anyhow!("unexpected query response")),
+    }
+}
+
 /// Asserts a transaction and all its receipts succeeded.
 pub fn assert_transaction_and_receipts_success(response: &RpcTransactionResponse) {
     match response.final_execution_status {
@@ -80,31 +178,64 @@ pub fn assert_transaction_and_receipts_success(response: &RpcTransactionResponse
         | TxExecutionStatus::Final => {}
     }
 
-    let outcome = response
-        .final_execution_outcome
-        .clone()
-        .expect("there should be an outcome")
-        .into_outcome();
-    matches!(outcome.status, FinalExecutionStatus::SuccessValue { .. });
-    for receipt_outcome in outcome.receipts_outcome.iter() {
-        match &receipt_outcome.outcome.status {
-            ExecutionStatusView::Unknown => panic!("receipt should have outcome"),
-            ExecutionStatusView::Failure(err) => panic!("receipt failed: {}", err),
-            ExecutionStatusView::SuccessValue(_) => {}
-            ExecutionStatusView::SuccessReceiptId(_) => {}
-        }
+    assert!(
+        is_transaction_and_receipts_success(response),
+        "transaction or one of its receipts failed: {:?}",
+        response.final_execution_outcome
+    );
+}
+
+/// Whether an executed transaction and all its receipts succeeded. Returns `false` if the response
+/// contains no outcome.
+pub fn is_transaction_and_receipts_success(response: &RpcTransactionResponse) -> bool {
+    let outcome = match &response.final_execution_outcome {
+        Some(outcome) => outcome.clone().into_outcome(),
+        None => return false,
+    };
+    if !matches!(outcome.status, FinalExecutionStatus::SuccessValue(_)) {
+        return false;
     }
+    outcome.receipts_outcome.iter().all(|receipt_outcome| {
+        matches!(
+            receipt_outcome.outcome.status,
+            ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_)
+        )
+    })
 }
 
-/// Maps `TxExecutionStatus` to integers s.t. higher numbers represent a higher finality.
-fn tx_execution_level(status: &TxExecutionStatus) -> u8 {
-    match status {
-        TxExecutionStatus::None => 0,
-        TxExecutionStatus::Included => 1,
-        TxExecutionStatus::ExecutedOptimistic => 2,
-        TxExecutionStatus::IncludedFinal => 3,
-        TxExecutionStatus::Executed => 4,
-        TxExecutionStatus::Final => 5,
+/// Parses a `TxExecutionStatus` from its RPC representation, e.g. `EXECUTED_OPTIMISTIC`. Accepts
+/// lowercase and kebab-case variants too, e.g. `executed-optimistic`.
+pub fn parse_tx_execution_status(s: &str) -> anyhow::Result<TxExecutionStatus> {
+    let normalized = s.to_uppercase().replace('-', "_");
+    serde_json::from_value(serde_json::Value::String(normalized))
+        .map_err(|_| anyhow::anyhow!("invalid tx execution status: {s}"))
+}
+
+/// Whether a transaction with `status` has been executed, i.e. its outcome is available.
+/// `IncludedFinal` does not imply execution.
+pub fn is_executed(status: &TxExecutionStatus) -> bool {
+    matches!(
+        status,
+        TxExecutionStatus::ExecutedOptimistic
+            | TxExecutionStatus::Executed
+            | TxExecutionStatus::Final
+    )
+}
+
+/// Whether a transaction with status `reached` satisfies `target`. Statuses are only partially
+/// ordered: neither of `ExecutedOptimistic` and `IncludedFinal` implies the other.
+pub fn has_reached(reached: &TxExecutionStatus, target: &TxExecutionStatus) -> bool {
+    let is_included_final = matches!(
+        reached,
+        TxExecutionStatus::IncludedFinal | TxExecutionStatus::Executed | TxExecutionStatus::Final
+    );
+    match target {
+        TxExecutionStatus::None => true,
+        TxExecutionStatus::Included => *reached != TxExecutionStatus::None,
+        TxExecutionStatus::ExecutedOptimistic => is_executed(reached),
+        TxExecutionStatus::IncludedFinal => is_included_final,
+        TxExecutionStatus::Executed => is_executed(reached) && is_included_final,
+        TxExecutionStatus::Final => *reached == TxExecutionStatus::Final,
     }
 }
 
@@ -120,7 +251,7 @@ pub fn check_tx_response(
     wait_until: TxExecutionStatus,
     response_check_severity: ResponseCheckSeverity,
 ) {
-    if tx_execution_level(&response.final_execution_status) < tx_execution_level(&wait_until) {
+    if !has_reached(&response.final_execution_status, &wait_until) {
         let msg = format!(
             "got final execution status {:#?}, expected at least {:#?}",
             response.final_execution_status, wait_until
@@ -135,11 +266,10 @@ pub fn check_tx_response(
             // If that ever changes, the outcome must be checked, hence the assert.
             assert!(response.final_execution_outcome.is_none());
         }
-        TxExecutionStatus::Included => {
-            unimplemented!("given how transactions are sent, this status is not yet returned")
+        TxExecutionStatus::Included | TxExecutionStatus::IncludedFinal => {
+            // The transaction was not yet executed, so there is no outcome to check.
         }
         TxExecutionStatus::ExecutedOptimistic
-        | TxExecutionStatus::IncludedFinal
         | TxExecutionStatus::Executed
         | TxExecutionStatus::Final => {
             // For now, only sending transactions that expect an empty success value.
@@ -184,7 +314,7 @@ fn check_outcome(
     }
 }
 
-fn warn_or_panic(msg: &str, response_check_severity: ResponseCheckSeverity) {
+pub(crate) fn warn_or_panic(msg: &str, response_check_severity: ResponseCheckSeverity) {
     match response_check_severity {
         ResponseCheckSeverity::Log => warn!("{msg}"),
         ResponseCheckSeverity::Assert => panic!("{msg}"),
@@ -197,11 +327,64 @@ mod tests {
 
     use crate::test_utils::connect_rpc_client;
 
+    #[test]
+    fn test_parse_tx_execution_status() {
+        assert_eq!(
+            parse_tx_execution_status("EXECUTED_OPTIMISTIC").unwrap(),
+            TxExecutionStatus::ExecutedOptimistic
+        );
+        assert_eq!(
+            parse_tx_execution_status("included-final").unwrap(),
+            TxExecutionStatus::IncludedFinal
+        );
+        assert!(parse_tx_execution_status("finalized").is_err());
+    }
+
+    #[test]
+    fn test_is_unknown_block() {
+        let unknown = JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+            RpcBlockError::UnknownBlock {
+                error_message: "DB Not Found".to_string(),
+            },
+        ));
+        assert!(is_unknown_block(&anyhow::Error::from(unknown)));
+        let not_synced: JsonRpcError<RpcBlockError> = JsonRpcError::ServerError(
+            JsonRpcServerError::HandlerError(RpcBlockError::NotSyncedYet),
+        );
+        assert!(!is_unknown_block(&anyhow::Error::from(not_synced)));
+        assert!(!is_unknown_block(&anyhow::anyhow!("timeout")));
+    }
+
+    #[test]
+    fn test_is_executed() {
+        assert!(!is_executed(&TxExecutionStatus::Included));
+        assert!(!is_executed(&TxExecutionStatus::IncludedFinal));
+        assert!(is_executed(&TxExecutionStatus::ExecutedOptimistic));
+        assert!(is_executed(&TxExecutionStatus::Final));
+
+        assert!(has_reached(
+            &TxExecutionStatus::Executed,
+            &TxExecutionStatus::IncludedFinal
+        ));
+        assert!(!has_reached(
+            &TxExecutionStatus::IncludedFinal,
+            &TxExecutionStatus::ExecutedOptimistic
+        ));
+        assert!(!has_reached(
+            &TxExecutionStatus::ExecutedOptimistic,
+            &TxExecutionStatus::IncludedFinal
+        ));
+        assert!(has_reached(
+            &TxExecutionStatus::IncludedFinal,
+            &TxExecutionStatus::Included
+        ));
+    }
+
     #[tokio::test]
     async fn test_get_block() -> anyhow::Result<()> {
         let block_ref = BlockReference::Finality(Finality::Final);
         let client = connect_rpc_client();
-        let block_view = get_block(&client, block_ref).await?;
+        let _block_view = get_block(&client, block_ref).await?;
         Ok(())
     }
 }
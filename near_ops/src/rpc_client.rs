@@ -0,0 +1,279 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use clap::Args;
+use near_jsonrpc_client::{
+    errors::JsonRpcError, methods::RpcMethod, JsonRpcClient, MethodCallResult,
+};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tower_layer::Layer;
+use tower_service::Service;
+
+const DEFAULT_POOL_SIZE: usize = 1024;
+const DEFAULT_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+const DEFAULT_TCP_KEEPALIVE_SECS: u64 = 60;
+const DEFAULT_REQUEST_TIMEOUT_MILLIS: u64 = 60_000;
+
+/// Configures where requests are sent and at which rate, shared by commands sending many requests.
+#[derive(Args, Clone, Debug)]
+pub struct SendArgs {
+    /// Address of the RPC node, e.g. `http://localhost:3030`.
+    #[arg(long)]
+    pub rpc_url: String,
+    /// Acts as upper bound on the number of concurrently open RPC requests.
+    #[arg(long)]
+    pub channel_buffer_size: usize,
+    /// After each tick (in microseconds) a request is sent. If the hardware cannot keep up with
+    /// that or if the NEAR node is congested, requests are sent at a slower rate.
+    #[arg(long)]
+    pub interval_duration_micros: u64,
+}
+
+impl SendArgs {
+    pub fn interval(&self) -> Duration {
+        Duration::from_micros(self.interval_duration_micros)
+    }
+}
+
+/// Configures the HTTP transport used to send requests to the RPC.
+#[derive(Args, Clone, Debug)]
+pub struct RpcClientArgs {
+    /// Maximum number of concurrently open requests, which for HTTP/1.1 bounds the number of open
+    /// connections (and file descriptors). Requests beyond that wait for a pooled connection.
+    #[arg(long, default_value_t = DEFAULT_POOL_SIZE)]
+    pub rpc_pool_size: usize,
+    /// Idle connections in the pool are closed after this many seconds.
+    #[arg(long, default_value_t = DEFAULT_POOL_IDLE_TIMEOUT_SECS)]
+    pub rpc_pool_idle_timeout_secs: u64,
+    /// Interval (in seconds) for TCP keep-alive probes on open connections.
+    #[arg(long, default_value_t = DEFAULT_TCP_KEEPALIVE_SECS)]
+    pub rpc_tcp_keepalive_secs: u64,
+    /// Speak HTTP/2 without prior negotiation, multiplexing requests over few connections.
+    #[arg(long)]
+    pub rpc_http2: bool,
+    /// Timeout (in milliseconds) of a single request, including waiting for the response.
+    #[arg(long, default_value_t = DEFAULT_REQUEST_TIMEOUT_MILLIS)]
+    pub rpc_request_timeout_millis: u64,
+}
+
+impl Default for RpcClientArgs {
+    fn default() -> Self {
+        Self {
+            rpc_pool_size: DEFAULT_POOL_SIZE,
+            rpc_pool_idle_timeout_secs: DEFAULT_POOL_IDLE_TIMEOUT_SECS,
+            rpc_tcp_keepalive_secs: DEFAULT_TCP_KEEPALIVE_SECS,
+            rpc_http2: false,
+            rpc_request_timeout_millis: DEFAULT_REQUEST_TIMEOUT_MILLIS,
+        }
+    }
+}
+
+/// A [`JsonRpcClient`] on top of a connection pool of bounded size, which keeps track of how
+/// well connections are reused.
+///
+/// Cloning is cheap and clones share the pool and the statistics.
+#[derive(Clone)]
+pub struct RpcClient {
+    client: JsonRpcClient,
+    /// Limits the number of in-flight requests to the pool size.
+    permits: Arc<Semaphore>,
+    stats: Arc<RpcClientStats>,
+}
+
+#[derive(Default)]
+struct RpcClientStats {
+    num_requests: AtomicU64,
+    num_connects: AtomicU64,
+    num_transport_errors: AtomicU64,
+}
+
+impl RpcClient {
+    pub fn new(server_addr: &str, args: &RpcClientArgs) -> anyhow::Result<Self> {
+        let stats = Arc::new(RpcClientStats::default());
+        // `JsonRpcClient::new_client` sets this header, which the RPC requires, but a client passed
+        // to `JsonRpcClient::with` must set it itself.
+        let mut builder = reqwest::Client::builder()
+            .default_headers(HeaderMap::from_iter([(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            )]))
+            .pool_max_idle_per_host(args.rpc_pool_size)
+            .pool_idle_timeout(Duration::from_secs(args.rpc_pool_idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(args.rpc_tcp_keepalive_secs))
+            .timeout(Duration::from_millis(args.rpc_request_timeout_millis))
+            .connector_layer(CountConnectsLayer {
+                stats: stats.clone(),
+            });
+        if args.rpc_http2 {
+            builder = builder
+                .http2_prior_knowledge()
+                .http2_keep_alive_interval(Duration::from_secs(args.rpc_tcp_keepalive_secs))
+                .http2_keep_alive_while_idle(true);
+        }
+        let http_client = builder.build()?;
+
+        Ok(Self {
+            client: JsonRpcClient::with(http_client).connect(server_addr),
+            permits: Arc::new(Semaphore::new(args.rpc_pool_size)),
+            stats,
+        })
+    }
+
+    /// Connects with the default transport configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client cannot be built.
+    pub fn connect(server_addr: &str) -> Self {
+        Self::new(server_addr, &RpcClientArgs::default()).expect("should build rpc client")
+    }
+
+    pub fn server_addr(&self) -> &str {
+        self.client.server_addr()
+    }
+
+    /// Waits until the pool can take another request and then sends `method`.
+    pub async fn call<M>(&self, method: M) -> MethodCallResult<M::Response, M::Error>
+    where
+        M: RpcMethod,
+    {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("semaphore should not be closed");
+        self.stats.num_requests.fetch_add(1, Ordering::Relaxed);
+        let result = self.client.call(method).await;
+        if let Err(JsonRpcError::TransportError(_)) = &result {
+            self.stats
+                .num_transport_errors
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    pub fn connection_stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            num_requests: self.stats.num_requests.load(Ordering::Relaxed),
+            num_connects: self.stats.num_connects.load(Ordering::Relaxed),
+            num_transport_errors: self.stats.num_transport_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of the requests sent by a [`RpcClient`] and its clones.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub num_requests: u64,
+    /// Number of attempts to establish a new connection.
+    pub num_connects: u64,
+    /// Requests that failed without a response from the RPC, e.g. due to timeouts.
+    pub num_transport_errors: u64,
+}
+
+impl ConnectionStats {
+    /// Fraction of requests that were sent over an already established connection.
+    pub fn reuse_ratio(&self) -> f64 {
+        if self.num_requests == 0 {
+            return 0.0;
+        }
+        self.num_requests.saturating_sub(self.num_connects) as f64 / self.num_requests as f64
+    }
+}
+
+impl fmt::Display for ConnectionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests over {} connections ({:.2}% reused), {} transport errors",
+            self.num_requests,
+            self.num_connects,
+            self.reuse_ratio() * 100.0,
+            self.num_transport_errors
+        )
+    }
+}
+
+/// Wraps the connector of the HTTP client, which is called only when the pool has no idle
+/// connection available, to count established connections.
+#[derive(Clone)]
+struct CountConnectsLayer {
+    stats: Arc<RpcClientStats>,
+}
+
+impl<S> Layer<S> for CountConnectsLayer {
+    type Service = CountConnects<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountConnects {
+            inner,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CountConnects<S> {
+    inner: S,
+    stats: Arc<RpcClientStats>,
+}
+
+impl<S, Req> Service<Req> for CountConnects<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.stats.num_connects.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_jsonrpc_client::methods::status::RpcStatusRequest;
+
+    use super::*;
+    use crate::test_utils::serve_recording_http_stand_in;
+
+    #[tokio::test]
+    async fn test_requests_are_json() -> anyhow::Result<()> {
+        let (url, mut requests) = serve_recording_http_stand_in("{}".to_string()).await?;
+        let client = RpcClient::connect(&url);
+        // The stand-in does not speak JSON-RPC, only the request matters.
+        let _ = client.call(RpcStatusRequest).await;
+        let request = requests.recv().await.unwrap().to_lowercase();
+        assert!(
+            request.contains("content-type: application/json"),
+            "{request}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reuse_ratio() {
+        let stats = ConnectionStats {
+            num_requests: 1000,
+            num_connects: 10,
+            num_transport_errors: 0,
+        };
+        assert_eq!(stats.reuse_ratio(), 0.99);
+        assert_eq!(ConnectionStats::default().reuse_ratio(), 0.0);
+    }
+}
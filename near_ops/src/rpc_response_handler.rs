@@ -8,16 +8,38 @@ use near_jsonrpc_client::{
 use near_primitives::views::TxExecutionStatus;
 use tokio::sync::mpsc::Receiver;
 
-use crate::rpc::check_tx_response;
+use crate::{
+    finality::FinalityTimings,
+    gas::TxCost,
+    rpc::{check_tx_response, warn_or_panic},
+};
 
 pub type RpcCallResult = Result<RpcTransactionResponse, JsonRpcError<RpcTransactionError>>;
 
+pub struct TxResponse {
+    pub result: RpcCallResult,
+    /// Available if the transaction was sent via
+    /// [`send_tx_and_record_finality`](crate::finality::send_tx_and_record_finality).
+    pub finality_timings: Option<FinalityTimings>,
+}
+
+impl From<RpcCallResult> for TxResponse {
+    fn from(result: RpcCallResult) -> Self {
+        Self {
+            result,
+            finality_timings: None,
+        }
+    }
+}
+
 pub struct RpcResponseHandler {
-    receiver: Receiver<RpcCallResult>,
     /// The `wait_until` value passed to transactions.
     wait_until: TxExecutionStatus,
     response_check_severity: ResponseCheckSeverity,
-    num_expected_responses: u64,
+    /// Requests that failed without a response from the RPC, e.g. due to timeouts.
+    num_transport_errors: u64,
+    finality_timings: Vec<FinalityTimings>,
+    tx_costs: Vec<TxCost>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -30,31 +52,33 @@ pub enum ResponseCheckSeverity {
 
 impl RpcResponseHandler {
     pub fn new(
-        receiver: Receiver<RpcCallResult>,
         wait_until: TxExecutionStatus,
         response_check_severity: ResponseCheckSeverity,
-        num_expected_responses: u64,
     ) -> Self {
         Self {
-            receiver,
             wait_until,
             response_check_severity,
-            num_expected_responses,
+            num_transport_errors: 0,
+            finality_timings: vec![],
+            tx_costs: vec![],
         }
     }
 
-    pub async fn handle_all_responses(&mut self) {
+    pub async fn handle_all_responses(
+        &mut self,
+        mut receiver: Receiver<TxResponse>,
+        num_expected_responses: u64,
+    ) {
         // Start timer after receiving the first response.
         let mut timer: Option<Instant> = None;
 
         let mut num_received = 0;
-        while num_received < self.num_expected_responses {
-            let response = match self.receiver.recv().await {
+        while num_received < num_expected_responses {
+            let response = match receiver.recv().await {
                 Some(res) => res,
                 None => {
                     warn!(
-                        "Expectet {} responses but channel closed after {num_received}",
-                        self.num_expected_responses
+                        "Expectet {num_expected_responses} responses but channel closed after {num_received}"
                     );
                     break;
                 }
@@ -64,13 +88,7 @@ impl RpcResponseHandler {
             if timer.is_none() {
                 timer = Some(Instant::now());
             }
-
-            let rpc_response = response.expect("rpc call should succeed");
-            check_tx_response(
-                rpc_response,
-                self.wait_until.clone(),
-                self.response_check_severity,
-            );
+            self.handle_response(response);
         }
 
         if let Some(timer) = timer {
@@ -79,5 +97,98 @@ impl RpcResponseHandler {
                 timer.elapsed().as_secs_f64()
             );
         }
+        self.log_transport_errors(num_received);
+    }
+
+    /// Checks a single response and collects its finality timings and costs. Callers receiving
+    /// responses themselves, e.g. to inspect them first, pass each of them here.
+    pub fn handle_response(&mut self, response: TxResponse) {
+        if let Some(finality_timings) = response.finality_timings {
+            self.finality_timings.push(finality_timings);
+        }
+        let rpc_response = match response.result {
+            Ok(rpc_response) => rpc_response,
+            // Under load requests may time out. Unless responses are asserted, that is part of
+            // the measurement rather than a reason to abort the run.
+            Err(JsonRpcError::TransportError(_))
+                if matches!(self.response_check_severity, ResponseCheckSeverity::Log) =>
+            {
+                self.num_transport_errors += 1;
+                return;
+            }
+            Err(err) => {
+                warn_or_panic(
+                    &format!("rpc call failed: {err}"),
+                    self.response_check_severity,
+                );
+                return;
+            }
+        };
+        self.tx_costs.extend(TxCost::from_response(&rpc_response));
+        check_tx_response(
+            rpc_response,
+            self.wait_until.clone(),
+            self.response_check_severity,
+        );
+    }
+
+    pub fn log_transport_errors(&self, num_received: u64) {
+        if self.num_transport_errors > 0 {
+            warn!(
+                "{} of {num_received} requests failed without response",
+                self.num_transport_errors
+            );
+        }
+    }
+
+    pub fn num_transport_errors(&self) -> u64 {
+        self.num_transport_errors
+    }
+
+    /// Timings of the handled responses that carried them.
+    pub fn take_finality_timings(&mut self) -> Vec<FinalityTimings> {
+        std::mem::take(&mut self.finality_timings)
+    }
+
+    /// Costs of the handled transactions whose execution was awaited.
+    pub fn take_tx_costs(&mut self) -> Vec<TxCost> {
+        std::mem::take(&mut self.tx_costs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use near_jsonrpc_client::errors::{JsonRpcTransportSendError, RpcTransportError};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_transport_errors_are_counted() {
+        let (sender, receiver) = mpsc::channel(2);
+        for _ in 0..2 {
+            sender.send(transport_error_response()).await.unwrap();
+        }
+        let mut handler =
+            RpcResponseHandler::new(TxExecutionStatus::None, ResponseCheckSeverity::Log);
+        handler.handle_all_responses(receiver, 2).await;
+        assert_eq!(handler.num_transport_errors(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "rpc call failed")]
+    fn test_transport_errors_panic_under_assert() {
+        let mut handler =
+            RpcResponseHandler::new(TxExecutionStatus::None, ResponseCheckSeverity::Assert);
+        handler.handle_response(transport_error_response());
+    }
+
+    fn transport_error_response() -> TxResponse {
+        let err = RpcTransportError::SendError(JsonRpcTransportSendError::PayloadSerializeError(
+            io::Error::other("timed out"),
+        ));
+        TxResponse::from(Err(JsonRpcError::TransportError(err)))
     }
 }
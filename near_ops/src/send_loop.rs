@@ -0,0 +1,312 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use near_jsonrpc_client::methods::{
+    broadcast_tx_async::RpcBroadcastTxAsyncRequest, send_tx::RpcSendTransactionRequest,
+};
+use near_primitives::{transaction::SignedTransaction, views::TxExecutionStatus};
+use tokio::{
+    sync::mpsc::{self, Receiver},
+    time,
+};
+
+use crate::{
+    finality::{send_tx_and_record_finality, FinalityLatencyReport},
+    gas::TxCost,
+    load_metrics::{load_metrics, ResponseOutcome},
+    rpc_client::RpcClient,
+    rpc_response_handler::{ResponseCheckSeverity, RpcResponseHandler, TxResponse},
+    tx_status_tracker::{BroadcastTxAsyncResult, SentTx, TxStatusReport, TxStatusTracker},
+};
+
+/// Sends transactions built by the caller at a fixed rate. Benchmarks differ only in the
+/// transactions they send, so the pacing, bounding of outstanding requests and handling of
+/// responses lives here.
+pub struct SendLoop {
+    client: RpcClient,
+    /// After each tick a transaction is sent. If the hardware cannot keep up with that or if the
+    /// NEAR node is congested, transactions are sent at a slower rate.
+    interval_duration: Duration,
+    /// Upper bound on the number of concurrently open RPC requests.
+    channel_buffer_size: usize,
+    /// Passed to `send_tx`.
+    wait_until: TxExecutionStatus,
+    /// See [`Self::with_finality_recording`].
+    record_finality: bool,
+    /// Applied to responses checked by [`Self::run`].
+    response_check_severity: ResponseCheckSeverity,
+}
+
+pub struct SendLoopReport {
+    pub send_duration: Duration,
+    /// Available if finality is recorded and `wait_until` is not `NONE`.
+    pub finality_latency: Option<FinalityLatencyReport>,
+    pub tx_costs: Vec<TxCost>,
+}
+
+impl SendLoopReport {
+    /// Collects the finality timings and costs of the responses handled by
+    /// `rpc_response_handler`.
+    pub fn new(send_duration: Duration, mut rpc_response_handler: RpcResponseHandler) -> Self {
+        let finality_timings = rpc_response_handler.take_finality_timings();
+        let finality_latency = if finality_timings.is_empty() {
+            None
+        } else {
+            let finality_latency = FinalityLatencyReport::new(finality_timings);
+            finality_latency.log_summary();
+            Some(finality_latency)
+        };
+        Self {
+            send_duration,
+            finality_latency,
+            tx_costs: rpc_response_handler.take_tx_costs(),
+        }
+    }
+}
+
+impl SendLoop {
+    /// Unexpected responses are logged, see [`Self::with_response_check_severity`]. Each
+    /// transaction is sent with a single `send_tx` request, see [`Self::with_finality_recording`].
+    pub fn new(
+        client: RpcClient,
+        interval_duration: Duration,
+        channel_buffer_size: usize,
+        wait_until: TxExecutionStatus,
+    ) -> Self {
+        Self {
+            client,
+            interval_duration,
+            channel_buffer_size,
+            wait_until,
+            record_finality: false,
+            response_check_severity: ResponseCheckSeverity::Log,
+        }
+    }
+
+    /// Records the time transactions take to reach each finality level up to `wait_until`, see
+    /// [`send_tx_and_record_finality`]. That takes up to three requests per transaction, so only
+    /// benchmarks reporting the timings should enable it.
+    pub fn with_finality_recording(mut self) -> Self {
+        self.record_finality = true;
+        self
+    }
+
+    /// Commands preparing accounts rather than measuring should use
+    /// [`ResponseCheckSeverity::Assert`], since later commands rely on their transactions.
+    pub fn with_response_check_severity(mut self, severity: ResponseCheckSeverity) -> Self {
+        self.response_check_severity = severity;
+        self
+    }
+
+    /// Sends `num_txs` transactions via `send_tx`, the `i`-th built by `next_tx(i)`, and checks
+    /// the responses.
+    pub async fn run(
+        &self,
+        num_txs: u64,
+        mut next_tx: impl FnMut(u64) -> anyhow::Result<SignedTransaction>,
+    ) -> anyhow::Result<SendLoopReport> {
+        let mut rpc_response_handler = self.response_handler();
+        let (send_duration, rpc_response_handler) = send_paced(
+            num_txs,
+            self.interval_duration,
+            self.channel_buffer_size,
+            |i| {
+                let transaction = next_tx(i)?;
+                Ok(send_tx(
+                    self.client.clone(),
+                    transaction,
+                    self.wait_until.clone(),
+                    self.record_finality,
+                ))
+            },
+            |receiver| async move {
+                rpc_response_handler
+                    .handle_all_responses(receiver, num_txs)
+                    .await;
+                rpc_response_handler
+            },
+        )
+        .await?;
+        Ok(SendLoopReport::new(send_duration, rpc_response_handler))
+    }
+
+    /// A handler checking responses like [`Self::run`] does, for callers of [`Self::run_tagged`]
+    /// which inspect responses before passing them on.
+    pub fn response_handler(&self) -> RpcResponseHandler {
+        RpcResponseHandler::new(self.wait_until.clone(), self.response_check_severity)
+    }
+
+    /// Like [`Self::run`], but `next_tx` also returns a tag for each transaction. The responses
+    /// along with their tags are passed to `handle_responses`, which runs while transactions are
+    /// sent. Returns the send duration and the output of `handle_responses`.
+    pub async fn run_tagged<T, F, R>(
+        &self,
+        num_txs: u64,
+        mut next_tx: impl FnMut(u64) -> anyhow::Result<(SignedTransaction, T)>,
+        handle_responses: impl FnOnce(Receiver<(T, TxResponse)>) -> F,
+    ) -> anyhow::Result<(Duration, R)>
+    where
+        T: Send + 'static,
+        F: Future<Output = R> + Send + 'static,
+        R: Send + 'static,
+    {
+        send_paced(
+            num_txs,
+            self.interval_duration,
+            self.channel_buffer_size,
+            |i| {
+                let (transaction, tag) = next_tx(i)?;
+                let response = send_tx(
+                    self.client.clone(),
+                    transaction,
+                    self.wait_until.clone(),
+                    self.record_finality,
+                );
+                Ok(async move { (tag, response.await) })
+            },
+            handle_responses,
+        )
+        .await
+    }
+
+    /// Like [`Self::run`], but sends transactions via `broadcast_tx_async` and hands the results
+    /// to the tracker built by `tracker`, which polls their status. `wait_until` is ignored.
+    pub async fn run_broadcast(
+        &self,
+        num_txs: u64,
+        mut next_tx: impl FnMut(u64) -> anyhow::Result<SignedTransaction>,
+        tracker: impl FnOnce(Receiver<BroadcastTxAsyncResult>) -> TxStatusTracker,
+    ) -> anyhow::Result<(Duration, TxStatusReport)> {
+        let (send_duration, report) = send_paced(
+            num_txs,
+            self.interval_duration,
+            self.channel_buffer_size,
+            |i| Ok(broadcast_tx_async(self.client.clone(), next_tx(i)?)),
+            |receiver| tracker(receiver).track_all(),
+        )
+        .await?;
+        report.log_summary();
+        Ok((send_duration, report))
+    }
+}
+
+/// Starts one request per tick of `interval_duration`, the `i`-th returned by `next_request(i)`,
+/// and passes the responses to `handle_responses`, which runs while requests are sent. Returns the
+/// send duration and the output of `handle_responses`.
+///
+/// At most `channel_buffer_size` requests are outstanding at any time. Requests are started in the
+/// order of `next_request`, so transactions of one signer usually reach the node in nonce order.
+pub async fn send_paced<M, F, H, R>(
+    num_requests: u64,
+    interval_duration: Duration,
+    channel_buffer_size: usize,
+    mut next_request: impl FnMut(u64) -> anyhow::Result<F>,
+    handle_responses: impl FnOnce(Receiver<M>) -> H,
+) -> anyhow::Result<(Duration, R)>
+where
+    M: Send + 'static,
+    F: Future<Output = M> + Send + 'static,
+    H: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    let (channel_tx, channel_rx) = mpsc::channel(channel_buffer_size);
+    let handler_task = tokio::spawn(handle_responses(channel_rx));
+
+    let mut interval = time::interval(interval_duration);
+    let timer = Instant::now();
+    for i in 0..num_requests {
+        interval.tick().await;
+        let response = next_request(i)?;
+        // Before a request is made, a permit to send into the channel is awaited. Hence buffer
+        // size limits the number of outstanding requests. This helps to avoid congestion.
+        let permit = channel_tx.clone().reserve_owned().await.unwrap();
+        tokio::spawn(async move {
+            permit.send(response.await);
+        });
+        if i > 0 && i % 10000 == 0 {
+            info!("num requests sent: {}", i);
+        }
+    }
+    let send_duration = timer.elapsed();
+    info!(
+        "Sent {} requests in {:.2} seconds",
+        num_requests,
+        send_duration.as_secs_f64()
+    );
+    // The handler stops once all senders are dropped, i.e. all responses are passed on.
+    drop(channel_tx);
+
+    let output = handler_task
+        .await
+        .expect("response handler task should succeed");
+    Ok((send_duration, output))
+}
+
+async fn send_tx(
+    client: RpcClient,
+    transaction: SignedTransaction,
+    wait_until: TxExecutionStatus,
+    record_finality: bool,
+) -> TxResponse {
+    let in_flight = load_metrics().start_request();
+    let response = if !record_finality || wait_until == TxExecutionStatus::None {
+        let request = RpcSendTransactionRequest {
+            signed_transaction: transaction,
+            wait_until,
+        };
+        TxResponse::from(client.call(request).await)
+    } else {
+        let (result, finality_timings) =
+            send_tx_and_record_finality(&client, transaction, wait_until).await;
+        TxResponse {
+            result,
+            finality_timings: Some(finality_timings),
+        }
+    };
+    in_flight.finish(ResponseOutcome::of_send_tx(&response.result));
+    response
+}
+
+async fn broadcast_tx_async(
+    client: RpcClient,
+    transaction: SignedTransaction,
+) -> BroadcastTxAsyncResult {
+    let hash = transaction.get_hash();
+    let sender_id = transaction.transaction.signer_id().clone();
+    let request = RpcBroadcastTxAsyncRequest {
+        signed_transaction: transaction,
+    };
+    let sent_at = Instant::now();
+    let in_flight = load_metrics().start_request();
+    let res = client.call(request).await.map(|_| SentTx {
+        hash,
+        sender_id,
+        sent_at,
+    });
+    in_flight.finish(ResponseOutcome::of_broadcast_tx_async(&res));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_client::RpcClientArgs;
+
+    #[tokio::test]
+    async fn test_run_stops_at_failing_tx() {
+        let client = RpcClient::new("http://127.0.0.1:1", &RpcClientArgs::default()).unwrap();
+        let send_loop = SendLoop::new(client, Duration::from_micros(1), 1, TxExecutionStatus::None);
+        let mut num_built = 0;
+        let result = send_loop
+            .run(10, |i| {
+                num_built += 1;
+                anyhow::bail!("cannot build tx {i}")
+            })
+            .await;
+        assert_eq!(result.err().unwrap().to_string(), "cannot build tx 0");
+        assert_eq!(num_built, 1);
+    }
+}
@@ -1,17 +1,23 @@
 use anyhow::Context;
-use near_jsonrpc_client::JsonRpcClient;
 use near_workspaces::{
     network::{Sandbox, ValidatorKey},
     Worker,
 };
 use std::path::PathBuf;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+use crate::rpc_client::RpcClient;
 
 // TODO make these constants parameters
 const RPC_ADDR: &str = "http://localhost:3030";
 const VALIDATOR_KEY_PATH: &str = "./near-sandbox-home";
 
-pub fn connect_rpc_client() -> JsonRpcClient {
-    JsonRpcClient::connect(RPC_ADDR)
+pub fn connect_rpc_client() -> RpcClient {
+    RpcClient::connect(RPC_ADDR)
 }
 
 // TODO use localnet, for consistency with how cli will be used
@@ -21,12 +27,49 @@ pub async fn connect_workspaces_to_sandbox(
 ) -> anyhow::Result<Worker<Sandbox>> {
     let worker = near_workspaces::sandbox()
         .rpc_addr(rpc_address)
-        .validator_key(ValidatorKey::HomeDir(validator_key.into()))
+        .validator_key(ValidatorKey::HomeDir(validator_key))
         .await
         .context("Is there an rpc node listening on {rpc_address}")?;
     Ok(worker)
 }
 
+/// Serves `body` to every HTTP request on a local port, standing in for endpoints like a node's
+/// `/metrics`. Returns the url to request.
+pub async fn serve_http_stand_in(body: String) -> anyhow::Result<String> {
+    let (url, _requests) = serve_recording_http_stand_in(body).await?;
+    Ok(url)
+}
+
+/// Like [`serve_http_stand_in`], but also returns the raw requests it receives.
+pub async fn serve_recording_http_stand_in(
+    body: String,
+) -> anyhow::Result<(String, mpsc::UnboundedReceiver<String>)> {
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.expect("should accept connection");
+            let body = body.clone();
+            let requests_tx = requests_tx.clone();
+            tokio::spawn(async move {
+                // The request must be read before responding.
+                let mut buf = [0; 4096];
+                if let Ok(len) = stream.read(&mut buf).await {
+                    // Nobody may be listening for requests.
+                    let _ = requests_tx.send(String::from_utf8_lossy(&buf[..len]).into_owned());
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok((format!("http://{addr}/metrics"), requests_rx))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
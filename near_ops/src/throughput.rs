@@ -0,0 +1,139 @@
+use std::{fs, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle, time};
+
+use crate::load_metrics::{load_metrics, LoadMetricsSnapshot, ResponseOutcome};
+
+const BUCKET_DURATION: Duration = Duration::from_secs(1);
+
+/// Transactions sent and responses received within one second of a run.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ThroughputSample {
+    /// Seconds since the start of the run at which the bucket starts.
+    pub second: u64,
+    pub sent: u64,
+    pub responded: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Requests awaiting a response at the end of the bucket.
+    pub in_flight: i64,
+}
+
+impl ThroughputSample {
+    fn new(second: u64, previous: &LoadMetricsSnapshot, current: &LoadMetricsSnapshot) -> Self {
+        Self {
+            second,
+            sent: current.txs_sent - previous.txs_sent,
+            responded: current.num_all_responses() - previous.num_all_responses(),
+            succeeded: current.num_responses(ResponseOutcome::Succeeded)
+                - previous.num_responses(ResponseOutcome::Succeeded),
+            failed: current.num_errors() - previous.num_errors(),
+            in_flight: current.outstanding_requests,
+        }
+    }
+}
+
+/// Writes `samples` as CSV with a header row.
+pub fn write_csv(samples: &[ThroughputSample], path: &Path) -> anyhow::Result<()> {
+    let mut csv = String::from("second,sent,responded,succeeded,failed,in_flight\n");
+    for sample in samples {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            sample.second,
+            sample.sent,
+            sample.responded,
+            sample.succeeded,
+            sample.failed,
+            sample.in_flight
+        ));
+    }
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+/// Records [`ThroughputSample`]s from [`load_metrics`] every second in the background.
+pub struct ThroughputRecorder {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Vec<ThroughputSample>>,
+}
+
+impl ThroughputRecorder {
+    pub fn spawn() -> Self {
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(record(stop_rx));
+        Self { stop, task }
+    }
+
+    /// Returns all samples, the last of which may cover less than a second.
+    pub async fn stop(self) -> Vec<ThroughputSample> {
+        // Sending fails only if the task finished, which it does only after receiving.
+        let _ = self.stop.send(());
+        self.task.await.expect("throughput recorder should succeed")
+    }
+}
+
+async fn record(mut stop: oneshot::Receiver<()>) -> Vec<ThroughputSample> {
+    let mut samples = vec![];
+    let mut previous = load_metrics().snapshot();
+    let mut interval = time::interval(BUCKET_DURATION);
+    // Buckets must not be skipped to keep `second` accurate.
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Burst);
+    // The first tick completes immediately.
+    interval.tick().await;
+    loop {
+        let stopped = tokio::select! {
+            _ = &mut stop => true,
+            _ = interval.tick() => false,
+        };
+        let current = load_metrics().snapshot();
+        let second = u64::try_from(samples.len()).unwrap();
+        samples.push(ThroughputSample::new(second, &previous, &current));
+        previous = current;
+        if stopped {
+            return samples;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_and_csv() -> anyhow::Result<()> {
+        let previous = LoadMetricsSnapshot {
+            txs_sent: 10,
+            ..Default::default()
+        };
+        let mut current = LoadMetricsSnapshot {
+            txs_sent: 25,
+            outstanding_requests: 7,
+            ..Default::default()
+        };
+        current.responses[ResponseOutcome::Succeeded.idx()] = 6;
+        current.responses[ResponseOutcome::Timeout.idx()] = 2;
+        let sample = ThroughputSample::new(3, &previous, &current);
+        assert_eq!(
+            sample,
+            ThroughputSample {
+                second: 3,
+                sent: 15,
+                responded: 8,
+                succeeded: 6,
+                failed: 2,
+                in_flight: 7,
+            }
+        );
+
+        let path = std::env::temp_dir().join(format!("throughput-{}.csv", std::process::id()));
+        write_csv(&[sample], &path)?;
+        let csv = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(
+            csv,
+            "second,sent,responded,succeeded,failed,in_flight\n3,15,8,6,2,7\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use near_primitives::{
+    hash::CryptoHash,
+    transaction::SignedTransaction,
+    types::{BlockHeight, ProtocolVersion},
+    utils::create_receipt_id_from_transaction,
+    views::BlockView,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver};
+
+use crate::{
+    latency::LatencySummary,
+    report::duration_ms,
+    rpc::{get_protocol_config, scan_blocks_up_to},
+    rpc_client::RpcClient,
+};
+
+/// A transaction to track, registered right before it is sent.
+pub struct TrackedTx {
+    signed_transaction: SignedTransaction,
+    sent_at: SystemTime,
+    /// Height of the latest final block known when the transaction was sent.
+    sent_at_height: BlockHeight,
+}
+
+impl TrackedTx {
+    pub fn new(signed_transaction: SignedTransaction, sent_at_height: BlockHeight) -> Self {
+        Self {
+            signed_transaction,
+            sent_at: SystemTime::now(),
+            sent_at_height,
+        }
+    }
+}
+
+/// Where and when a transaction was included and its receipt executed.
+///
+/// Latencies in seconds compare the local time of sending with block timestamps, so they assume
+/// clocks of the benchmark host and the block producers are in sync.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TxLifecycle {
+    pub tx_hash: CryptoHash,
+    pub sent_at_height: BlockHeight,
+    pub included_height: Option<BlockHeight>,
+    pub executed_height: Option<BlockHeight>,
+    #[serde(rename = "inclusion_latency_ms", with = "duration_ms::option")]
+    pub inclusion_latency: Option<Duration>,
+    #[serde(rename = "execution_latency_ms", with = "duration_ms::option")]
+    pub execution_latency: Option<Duration>,
+}
+
+impl TxLifecycle {
+    /// Inclusion latency in blocks. Since `sent_at_height` refers to a final block, this includes
+    /// the lag of finality behind the head of the chain (usually two blocks).
+    pub fn inclusion_latency_blocks(&self) -> Option<u64> {
+        self.included_height
+            .map(|height| height.saturating_sub(self.sent_at_height))
+    }
+}
+
+/// Detects when transactions are included and executed by scanning the chunks of new blocks,
+/// which avoids one `tx` status request per transaction.
+///
+/// A transaction's receipt is considered executed:
+///
+/// - in the block including the transaction if the receiver is the sender, since nearcore executes
+///   such local receipts right after converting the transaction,
+/// - otherwise in the first block with a chunk that carries the receipt to the receiver's shard.
+///   That is a later block, also if sender and receiver are on the same shard.
+///
+/// Receipts delayed due to congestion are not detected, they are reported as executed when
+/// forwarded.
+pub struct TxLifecycleTracker {
+    rpc_client: RpcClient,
+    protocol_version: ProtocolVersion,
+    receiver: UnboundedReceiver<TrackedTx>,
+    new_blocks: broadcast::Receiver<BlockView>,
+    /// Transactions not included and executed within this many blocks after being sent are
+    /// given up on.
+    timeout_blocks: u64,
+    last_scanned_height: BlockHeight,
+    pending_txs: HashMap<CryptoHash, TrackedTx>,
+    /// Included transactions and the time they were sent, keyed by the id of their not yet
+    /// executed receipt.
+    pending_receipts: HashMap<CryptoHash, (TxLifecycle, SystemTime)>,
+    lifecycles: Vec<TxLifecycle>,
+}
+
+impl TxLifecycleTracker {
+    /// Blocks above `start_height` will be scanned.
+    pub async fn new(
+        rpc_client: RpcClient,
+        receiver: UnboundedReceiver<TrackedTx>,
+        new_blocks: broadcast::Receiver<BlockView>,
+        start_height: BlockHeight,
+        timeout_blocks: u64,
+    ) -> anyhow::Result<Self> {
+        let protocol_config = get_protocol_config(&rpc_client).await?;
+        Ok(Self {
+            rpc_client,
+            protocol_version: protocol_config.protocol_version,
+            receiver,
+            new_blocks,
+            timeout_blocks,
+            last_scanned_height: start_height,
+            pending_txs: HashMap::new(),
+            pending_receipts: HashMap::new(),
+            lifecycles: vec![],
+        })
+    }
+
+    /// Scans new blocks until the channel of tracked transactions is closed and all of them are
+    /// executed or timed out.
+    ///
+    /// Blocks that cannot be scanned due to RPC errors are retried once the next block is received.
+    pub async fn run(mut self) -> TxLifecycleReport {
+        let mut channel_open = true;
+        loop {
+            tokio::select! {
+                msg = self.receiver.recv(), if channel_open => match msg {
+                    Some(tx) => self.insert(tx),
+                    None => channel_open = false,
+                },
+                block = self.new_blocks.recv() => match block {
+                    Ok(block) => {
+                        // Transactions must be known before the block including them is scanned.
+                        while let Ok(tx) = self.receiver.try_recv() {
+                            self.insert(tx);
+                        }
+                        if let Err(err) = self.scan_up_to(block).await {
+                            warn!("failed to scan blocks, retrying with the next block: {err}");
+                        }
+                    }
+                    // Skipped blocks are fetched when the next block is received.
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+            if !channel_open && self.pending_txs.is_empty() && self.pending_receipts.is_empty() {
+                break;
+            }
+        }
+
+        // Keep what is known about transactions that did not complete.
+        for (_, tx) in self.pending_txs.drain() {
+            self.lifecycles.push(TxLifecycle {
+                tx_hash: tx.signed_transaction.get_hash(),
+                sent_at_height: tx.sent_at_height,
+                included_height: None,
+                executed_height: None,
+                inclusion_latency: None,
+                execution_latency: None,
+            });
+        }
+        self.lifecycles.extend(
+            self.pending_receipts
+                .drain()
+                .map(|(_, (lifecycle, _))| lifecycle),
+        );
+
+        TxLifecycleReport::new(self.lifecycles)
+    }
+
+    fn insert(&mut self, tx: TrackedTx) {
+        self.pending_txs
+            .insert(tx.signed_transaction.get_hash(), tx);
+    }
+
+    /// Scans all blocks between the last scanned one and `block`. On error, the heights that were
+    /// not scanned yet are scanned on the next call.
+    async fn scan_up_to(&mut self, block: BlockView) -> anyhow::Result<()> {
+        let client = self.rpc_client.clone();
+        let mut last_scanned_height = self.last_scanned_height;
+        let result =
+            scan_blocks_up_to(&client, &mut last_scanned_height, block, |block, chunks| {
+                let header = ScannedHeader::new(&block);
+                for chunk in chunks.iter() {
+                    self.scan_chunk(
+                        &header,
+                        chunk.transactions.iter().map(|tx| tx.hash),
+                        chunk.receipts.iter().map(|receipt| receipt.receipt_id),
+                    );
+                }
+            })
+            .await;
+        self.last_scanned_height = last_scanned_height;
+        result?;
+        self.expire_pending(last_scanned_height);
+        Ok(())
+    }
+
+    /// Scans the hashes of transactions and ids of receipts in a chunk of the block with `header`.
+    fn scan_chunk(
+        &mut self,
+        header: &ScannedHeader,
+        tx_hashes: impl Iterator<Item = CryptoHash>,
+        receipt_ids: impl Iterator<Item = CryptoHash>,
+    ) {
+        let height = header.height;
+        let block_time = header.time;
+
+        for receipt_id in receipt_ids {
+            if let Some((mut lifecycle, sent_at)) = self.pending_receipts.remove(&receipt_id) {
+                lifecycle.executed_height = Some(height);
+                lifecycle.execution_latency =
+                    Some(block_time.duration_since(sent_at).unwrap_or(Duration::ZERO));
+                self.lifecycles.push(lifecycle);
+            }
+        }
+
+        for tx_hash in tx_hashes {
+            let Some(tx) = self.pending_txs.remove(&tx_hash) else {
+                continue;
+            };
+            let inclusion_latency = block_time
+                .duration_since(tx.sent_at)
+                .unwrap_or(Duration::ZERO);
+            let mut lifecycle = TxLifecycle {
+                tx_hash,
+                sent_at_height: tx.sent_at_height,
+                included_height: Some(height),
+                executed_height: None,
+                inclusion_latency: Some(inclusion_latency),
+                execution_latency: None,
+            };
+
+            let transaction = &tx.signed_transaction.transaction;
+            if transaction.signer_id() == transaction.receiver_id() {
+                lifecycle.executed_height = Some(height);
+                lifecycle.execution_latency = Some(inclusion_latency);
+                self.lifecycles.push(lifecycle);
+            } else {
+                let receipt_id = create_receipt_id_from_transaction(
+                    self.protocol_version,
+                    &tx.signed_transaction,
+                    &header.prev_hash,
+                    &header.hash,
+                );
+                self.pending_receipts
+                    .insert(receipt_id, (lifecycle, tx.sent_at));
+            }
+        }
+    }
+
+    fn expire_pending(&mut self, height: BlockHeight) {
+        let timeout_blocks = self.timeout_blocks;
+        let is_expired = |sent_at_height: BlockHeight| height > sent_at_height + timeout_blocks;
+
+        let expired_txs: Vec<CryptoHash> = self
+            .pending_txs
+            .iter()
+            .filter(|(_, tx)| is_expired(tx.sent_at_height))
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in expired_txs {
+            let tx = self.pending_txs.remove(&hash).unwrap();
+            self.lifecycles.push(TxLifecycle {
+                tx_hash: hash,
+                sent_at_height: tx.sent_at_height,
+                included_height: None,
+                executed_height: None,
+                inclusion_latency: None,
+                execution_latency: None,
+            });
+        }
+
+        let expired_receipts: Vec<CryptoHash> = self
+            .pending_receipts
+            .iter()
+            .filter(|(_, (lifecycle, _))| is_expired(lifecycle.sent_at_height))
+            .map(|(receipt_id, _)| *receipt_id)
+            .collect();
+        for receipt_id in expired_receipts {
+            let (lifecycle, _) = self.pending_receipts.remove(&receipt_id).unwrap();
+            self.lifecycles.push(lifecycle);
+        }
+    }
+}
+
+/// The fields of a block header needed to scan its chunks.
+struct ScannedHeader {
+    height: BlockHeight,
+    hash: CryptoHash,
+    prev_hash: CryptoHash,
+    time: SystemTime,
+}
+
+impl ScannedHeader {
+    fn new(block: &BlockView) -> Self {
+        Self {
+            height: block.header.height,
+            hash: block.header.hash,
+            prev_hash: block.header.prev_hash,
+            time: UNIX_EPOCH + Duration::from_nanos(block.header.timestamp_nanosec),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TxLifecycleReport {
+    pub num_tracked: usize,
+    pub num_included: usize,
+    pub num_executed: usize,
+    pub inclusion_latency: LatencySummary,
+    pub execution_latency: LatencySummary,
+    pub mean_inclusion_latency_blocks: f64,
+    pub max_inclusion_latency_blocks: u64,
+    pub per_tx: Vec<TxLifecycle>,
+}
+
+impl TxLifecycleReport {
+    pub fn new(per_tx: Vec<TxLifecycle>) -> Self {
+        let inclusion_latencies: Vec<Duration> = per_tx
+            .iter()
+            .filter_map(|tx| tx.inclusion_latency)
+            .collect();
+        let execution_latencies: Vec<Duration> = per_tx
+            .iter()
+            .filter_map(|tx| tx.execution_latency)
+            .collect();
+        let inclusion_blocks: Vec<u64> = per_tx
+            .iter()
+            .filter_map(|tx| tx.inclusion_latency_blocks())
+            .collect();
+        let mean_inclusion_latency_blocks = if inclusion_blocks.is_empty() {
+            0.0
+        } else {
+            inclusion_blocks.iter().sum::<u64>() as f64 / inclusion_blocks.len() as f64
+        };
+
+        Self {
+            num_tracked: per_tx.len(),
+            num_included: inclusion_latencies.len(),
+            num_executed: per_tx
+                .iter()
+                .filter(|tx| tx.executed_height.is_some())
+                .count(),
+            inclusion_latency: LatencySummary::from_durations(&inclusion_latencies),
+            execution_latency: LatencySummary::from_durations(&execution_latencies),
+            mean_inclusion_latency_blocks,
+            max_inclusion_latency_blocks: inclusion_blocks.into_iter().max().unwrap_or(0),
+            per_tx,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Tracked {} txs by scanning blocks: {} included, {} executed",
+            self.num_tracked, self.num_included, self.num_executed
+        );
+        info!(
+            "Inclusion latency: {:.2} blocks on average, at most {} blocks",
+            self.mean_inclusion_latency_blocks, self.max_inclusion_latency_blocks
+        );
+        info!("Inclusion latency: {}", self.inclusion_latency);
+        info!("Execution latency: {}", self.execution_latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{InMemorySigner, KeyType, Signer};
+    use near_primitives::{types::AccountId, version::PROTOCOL_VERSION};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::rpc_client::RpcClientArgs;
+
+    fn lifecycle(included_height: Option<BlockHeight>, executed: bool) -> TxLifecycle {
+        TxLifecycle {
+            tx_hash: CryptoHash::default(),
+            sent_at_height: 100,
+            included_height,
+            executed_height: included_height.filter(|_| executed),
+            inclusion_latency: included_height.map(|_| Duration::from_secs(1)),
+            execution_latency: included_height
+                .filter(|_| executed)
+                .map(|_| Duration::from_secs(2)),
+        }
+    }
+
+    #[test]
+    fn test_report_counts_incomplete_txs() {
+        let report = TxLifecycleReport::new(vec![
+            lifecycle(Some(102), true),
+            lifecycle(Some(105), false),
+            lifecycle(None, false),
+        ]);
+        assert_eq!(report.num_tracked, 3);
+        assert_eq!(report.num_included, 2);
+        assert_eq!(report.num_executed, 1);
+        assert_eq!(report.mean_inclusion_latency_blocks, 3.5);
+        assert_eq!(report.max_inclusion_latency_blocks, 5);
+    }
+
+    fn header(height: BlockHeight, time: SystemTime) -> ScannedHeader {
+        ScannedHeader {
+            height,
+            hash: CryptoHash::hash_bytes(&height.to_le_bytes()),
+            prev_hash: CryptoHash::hash_bytes(&(height - 1).to_le_bytes()),
+            time,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receipt_to_other_account_on_same_shard_executes_later() {
+        let (_, receiver) = mpsc::unbounded_channel();
+        let (_, new_blocks) = broadcast::channel(1);
+        let mut tracker = TxLifecycleTracker {
+            rpc_client: RpcClient::new("http://127.0.0.1:1", &RpcClientArgs::default()).unwrap(),
+            protocol_version: PROTOCOL_VERSION,
+            receiver,
+            new_blocks,
+            timeout_blocks: 10,
+            last_scanned_height: 100,
+            pending_txs: HashMap::new(),
+            pending_receipts: HashMap::new(),
+            lifecycles: vec![],
+        };
+
+        // On a single shard, sender and receiver of both transactions are on the same shard.
+        let sender_id: AccountId = "user_0.test.near".parse().unwrap();
+        let signer = Signer::from(InMemorySigner::from_random(
+            sender_id.clone(),
+            KeyType::ED25519,
+        ));
+        let transfer = SignedTransaction::send_money(
+            1,
+            sender_id.clone(),
+            "user_1.test.near".parse().unwrap(),
+            &signer,
+            1,
+            CryptoHash::default(),
+        );
+        let to_self = SignedTransaction::send_money(
+            2,
+            sender_id.clone(),
+            sender_id,
+            &signer,
+            1,
+            CryptoHash::default(),
+        );
+        tracker.insert(TrackedTx::new(transfer.clone(), 100));
+        tracker.insert(TrackedTx::new(to_self.clone(), 100));
+
+        let now = SystemTime::now();
+        let included = header(101, now + Duration::from_secs(1));
+        tracker.scan_chunk(
+            &included,
+            [transfer.get_hash(), to_self.get_hash()].into_iter(),
+            std::iter::empty(),
+        );
+        // Only the local receipt of the transaction to self is executed right away.
+        assert_eq!(tracker.lifecycles.len(), 1);
+        assert_eq!(tracker.lifecycles[0].tx_hash, to_self.get_hash());
+        assert_eq!(tracker.lifecycles[0].executed_height, Some(101));
+        assert_eq!(tracker.pending_receipts.len(), 1);
+
+        let receipt_id = create_receipt_id_from_transaction(
+            PROTOCOL_VERSION,
+            &transfer,
+            &included.prev_hash,
+            &included.hash,
+        );
+        tracker.scan_chunk(
+            &header(102, now + Duration::from_secs(2)),
+            std::iter::empty(),
+            [receipt_id].into_iter(),
+        );
+        let lifecycle = &tracker.lifecycles[1];
+        assert_eq!(lifecycle.tx_hash, transfer.get_hash());
+        assert_eq!(lifecycle.included_height, Some(101));
+        assert_eq!(lifecycle.executed_height, Some(102));
+        assert!(lifecycle.execution_latency > lifecycle.inclusion_latency);
+        assert!(tracker.pending_receipts.is_empty());
+    }
+}
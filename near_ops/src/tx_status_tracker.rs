@@ -0,0 +1,232 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use near_jsonrpc_client::{
+    errors::JsonRpcError,
+    methods::{
+        broadcast_tx_async::RpcBroadcastTxAsyncError,
+        tx::{RpcTransactionStatusRequest, TransactionInfo},
+    },
+};
+use near_primitives::{hash::CryptoHash, types::AccountId, views::TxExecutionStatus};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        mpsc::{self, error::TryRecvError, Receiver},
+        Semaphore,
+    },
+    task::JoinSet,
+    time,
+};
+
+use crate::{
+    gas::TxCost,
+    latency::LatencySummary,
+    rpc::{has_reached, is_executed, is_transaction_and_receipts_success},
+    rpc_client::RpcClient,
+};
+
+/// A transaction that was submitted via `broadcast_tx_async`.
+#[derive(Clone, Debug)]
+pub struct SentTx {
+    pub hash: CryptoHash,
+    pub sender_id: AccountId,
+    pub sent_at: Instant,
+}
+
+pub type BroadcastTxAsyncResult = Result<SentTx, JsonRpcError<RpcBroadcastTxAsyncError>>;
+
+/// Polls the `tx` status of transactions submitted via `broadcast_tx_async` until they are
+/// executed, measuring how long inclusion and execution took.
+///
+/// Latencies are measured with the granularity of `poll_interval`.
+pub struct TxStatusTracker {
+    rpc_client: RpcClient,
+    receiver: Receiver<BroadcastTxAsyncResult>,
+    poll_interval: Duration,
+    /// Upper bound on the number of concurrently open `tx` requests.
+    max_concurrent_polls: usize,
+    /// Transactions that are not executed within this duration after being sent are given up on.
+    timeout: Duration,
+}
+
+struct PendingTx {
+    tx: SentTx,
+    included_after: Option<Duration>,
+    done: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TxStatusReport {
+    pub num_tracked: u64,
+    pub num_broadcast_errors: u64,
+    /// Executed transactions whose outcome or receipts failed.
+    pub num_failed: u64,
+    pub num_timed_out: u64,
+    /// Measured from submission, with the granularity of the poll interval.
+    pub inclusion_latency: LatencySummary,
+    pub execution_latency: LatencySummary,
+    #[serde(skip)]
+    inclusion_latencies: Vec<Duration>,
+    #[serde(skip)]
+    execution_latencies: Vec<Duration>,
+    #[serde(skip)]
+    pub tx_costs: Vec<TxCost>,
+}
+
+impl TxStatusTracker {
+    pub fn new(
+        rpc_client: RpcClient,
+        receiver: Receiver<BroadcastTxAsyncResult>,
+        poll_interval: Duration,
+        max_concurrent_polls: usize,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            rpc_client,
+            receiver,
+            poll_interval,
+            max_concurrent_polls,
+            timeout,
+        }
+    }
+
+    /// Tracks transactions until the channel is closed and all received transactions are either
+    /// executed or timed out.
+    ///
+    /// The receiver is drained by a separate task, so senders are not blocked while polling.
+    pub async fn track_all(self) -> TxStatusReport {
+        let Self {
+            rpc_client,
+            mut receiver,
+            poll_interval,
+            max_concurrent_polls,
+            timeout,
+        } = self;
+        let (sent_tx, mut sent_rx) = mpsc::unbounded_channel();
+        let receive_task = tokio::spawn(async move {
+            let mut num_broadcast_errors = 0;
+            while let Some(msg) = receiver.recv().await {
+                match msg {
+                    Ok(tx) => sent_tx.send(tx).expect("polling should be running"),
+                    Err(err) => {
+                        warn!("broadcast_tx_async failed: {err}");
+                        num_broadcast_errors += 1;
+                    }
+                }
+            }
+            num_broadcast_errors
+        });
+
+        let mut report = TxStatusReport::default();
+        let mut pending: Vec<PendingTx> = vec![];
+        let permits = Arc::new(Semaphore::new(max_concurrent_polls));
+        let mut interval = time::interval(poll_interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let channel_open = loop {
+                match sent_rx.try_recv() {
+                    Ok(tx) => {
+                        report.num_tracked += 1;
+                        pending.push(PendingTx {
+                            tx,
+                            included_after: None,
+                            done: false,
+                        });
+                    }
+                    Err(TryRecvError::Empty) => break true,
+                    Err(TryRecvError::Disconnected) => break false,
+                }
+            };
+            poll(&rpc_client, &permits, timeout, &mut pending, &mut report).await;
+            if !channel_open && pending.is_empty() {
+                break;
+            }
+        }
+
+        report.num_broadcast_errors = receive_task.await.expect("receiving should succeed");
+        report.inclusion_latency = LatencySummary::from_durations(&report.inclusion_latencies);
+        report.execution_latency = LatencySummary::from_durations(&report.execution_latencies);
+        report
+    }
+}
+
+/// Requests the status of all `pending` transactions, with at most as many requests in flight as
+/// there are `permits`.
+async fn poll(
+    rpc_client: &RpcClient,
+    permits: &Arc<Semaphore>,
+    timeout: Duration,
+    pending: &mut Vec<PendingTx>,
+    report: &mut TxStatusReport,
+) {
+    let mut requests = JoinSet::new();
+    for (idx, pending_tx) in pending.iter().enumerate() {
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let client = rpc_client.clone();
+        let request = RpcTransactionStatusRequest {
+            transaction_info: TransactionInfo::TransactionId {
+                tx_hash: pending_tx.tx.hash,
+                sender_account_id: pending_tx.tx.sender_id.clone(),
+            },
+            wait_until: TxExecutionStatus::None,
+        };
+        requests.spawn(async move {
+            let result = client.call(request).await;
+            drop(permit);
+            (idx, result)
+        });
+    }
+
+    while let Some(joined) = requests.join_next().await {
+        let (idx, result) = joined.expect("join should succeed");
+        let pending_tx = &mut pending[idx];
+        let elapsed = pending_tx.tx.sent_at.elapsed();
+
+        // Errors like an unknown transaction are expected until the tx reaches the node.
+        if let Ok(response) = result {
+            let status = &response.final_execution_status;
+            if pending_tx.included_after.is_none()
+                && has_reached(status, &TxExecutionStatus::Included)
+            {
+                pending_tx.included_after = Some(elapsed);
+                report.inclusion_latencies.push(elapsed);
+            }
+            if is_executed(status) {
+                report.execution_latencies.push(elapsed);
+                report.tx_costs.extend(TxCost::from_response(&response));
+                if !is_transaction_and_receipts_success(&response) {
+                    report.num_failed += 1;
+                }
+                pending_tx.done = true;
+                continue;
+            }
+        }
+
+        if elapsed > timeout {
+            report.num_timed_out += 1;
+            pending_tx.done = true;
+        }
+    }
+
+    pending.retain(|pending_tx| !pending_tx.done);
+}
+
+impl TxStatusReport {
+    pub fn log_summary(&self) {
+        info!(
+            "Tracked {} txs: {} broadcast errors, {} failed, {} timed out",
+            self.num_tracked, self.num_broadcast_errors, self.num_failed, self.num_timed_out
+        );
+        info!("Inclusion latency: {}", self.inclusion_latency);
+        info!("Execution latency: {}", self.execution_latency);
+    }
+}
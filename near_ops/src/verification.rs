@@ -0,0 +1,369 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use log::{info, warn};
+use near_crypto::PublicKey;
+use near_primitives::{
+    hash::CryptoHash,
+    serialize::dec_format,
+    types::{AccountId, Balance, BlockHeight, Nonce},
+    views::BlockView,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinSet, time};
+
+use crate::{
+    account::Account,
+    rpc::{get_latest_block, scan_blocks_up_to, view_access_key, view_account},
+    rpc_client::RpcClient,
+};
+
+/// Bounds the size of the report if many transactions were dropped.
+pub const MAX_REPORTED_DROPPED_TXS: usize = 10;
+
+#[derive(Args, Debug)]
+pub struct VerificationArgs {
+    /// After the run, verify that every sent transaction is included in a chunk, that the on-chain
+    /// nonces of all accounts match the persisted ones and that the total balance of the accounts
+    /// decreased only by the balance burnt. Assumes no other transactions affect the accounts or
+    /// burn gas while the benchmark runs.
+    #[arg(long)]
+    pub verify: bool,
+    /// With `--verify`, accounts are considered settled once their state did not change during
+    /// this many seconds.
+    #[arg(long, default_value_t = 5)]
+    pub verify_settle_secs: u64,
+    /// With `--verify`, give up waiting for accounts to settle after this many seconds. Accounts
+    /// that still change are verified anyway and listed in the report.
+    #[arg(long, default_value_t = 120)]
+    pub verify_timeout_secs: u64,
+}
+
+/// On-chain state of an [`Account`] and its access keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountState {
+    pub balance: Balance,
+    /// Nonces of access keys in the order of [`Account::public_keys`].
+    pub nonces: Vec<Nonce>,
+}
+
+/// Queries the state of all `accounts`, returned in the same order.
+pub async fn query_account_states(
+    client: &RpcClient,
+    accounts: &[Account],
+) -> anyhow::Result<Vec<AccountState>> {
+    let mut tasks = JoinSet::new();
+    // Use an interval to avoid overwhelming the node with requests.
+    let mut interval = time::interval(Duration::from_micros(150));
+    for (idx, account) in accounts.iter().enumerate() {
+        interval.tick().await;
+        let client = client.clone();
+        let account_id = account.id.clone();
+        let public_keys: Vec<_> = account.public_keys().cloned().collect();
+        tasks.spawn(async move {
+            let account_view = view_account(&client, account_id.clone()).await?;
+            let mut nonces = Vec::with_capacity(public_keys.len());
+            for public_key in public_keys {
+                nonces.push(
+                    view_access_key(&client, account_id.clone(), public_key)
+                        .await?
+                        .nonce,
+                );
+            }
+            let state = AccountState {
+                balance: account_view.amount,
+                nonces,
+            };
+            anyhow::Ok((idx, state))
+        });
+    }
+
+    let mut states = vec![None; accounts.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, state) = joined.expect("join should succeed")?;
+        states[idx] = Some(state);
+    }
+    Ok(states.into_iter().map(Option::unwrap).collect())
+}
+
+/// Tokens burnt and transactions included in the chunks of a range of blocks.
+#[derive(Debug, Default)]
+pub struct ChunkScan {
+    pub balance_burnt: Balance,
+    pub included_txs: HashSet<CryptoHash>,
+}
+
+/// Scans all chunks of blocks with heights in `(from_height, to.height]`.
+///
+/// Chunk headers carry the balance burnt when applying the previous chunk of the shard, so the
+/// range should extend a few blocks beyond the activity of interest.
+pub async fn scan_chunks(
+    client: &RpcClient,
+    from_height: BlockHeight,
+    to: BlockView,
+) -> anyhow::Result<ChunkScan> {
+    let mut scan = ChunkScan::default();
+    let mut last_scanned_height = from_height;
+    scan_blocks_up_to(client, &mut last_scanned_height, to, |block, chunks| {
+        scan.balance_burnt += block
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.height_included == block.header.height)
+            .map(|chunk| chunk.balance_burnt)
+            .sum::<Balance>();
+        scan.included_txs.extend(
+            chunks
+                .iter()
+                .flat_map(|chunk| chunk.transactions.iter().map(|tx| tx.hash)),
+        );
+    })
+    .await?;
+    Ok(scan)
+}
+
+/// Returns the transactions in `sent` that are not in `included`, in the order they were sent.
+fn find_dropped_txs(sent: &[CryptoHash], included: &HashSet<CryptoHash>) -> Vec<CryptoHash> {
+    sent.iter()
+        .filter(|hash| !included.contains(hash))
+        .copied()
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NonceMismatch {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    /// Nonce of the locally persisted [`Account`].
+    pub local: Nonce,
+    pub on_chain: Nonce,
+}
+
+/// Compares the state of accounts after a native transfer benchmark with the state before and with
+/// what was sent.
+///
+/// Since senders and receivers are the same set of accounts, transfers move tokens only within the
+/// set and only burnt gas decreases the total balance. This assumes the benchmark is the only
+/// source of transactions affecting the accounts and burning gas during the verified range of
+/// blocks.
+///
+/// Transactions are counted by their inclusion in chunks rather than by the increase of nonces,
+/// since nonces only need to increase: a dropped transaction followed by one with a higher nonce
+/// of the same key leaves no trace in the nonces.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerificationReport {
+    pub num_accounts: usize,
+    pub num_txs_sent: u64,
+    /// Sent transactions found in the chunks of the verified blocks.
+    pub num_txs_included: u64,
+    /// Up to [`MAX_REPORTED_DROPPED_TXS`] sent transactions not found in any chunk.
+    pub dropped_txs: Vec<CryptoHash>,
+    /// Accounts whose state still changed when the verification timeout passed. Their nonces and
+    /// balances may not be final.
+    pub unsettled_accounts: Vec<AccountId>,
+    pub nonce_mismatches: Vec<NonceMismatch>,
+    #[serde(with = "dec_format")]
+    pub total_balance_before: Balance,
+    #[serde(with = "dec_format")]
+    pub total_balance_after: Balance,
+    /// Burnt in chunks between the start of the benchmark and verification.
+    #[serde(with = "dec_format")]
+    pub balance_burnt: Balance,
+}
+
+impl VerificationReport {
+    pub fn is_balance_conserved(&self) -> bool {
+        self.total_balance_before.checked_sub(self.balance_burnt) == Some(self.total_balance_after)
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.unsettled_accounts.is_empty()
+            && self.nonce_mismatches.is_empty()
+            && self.num_txs_included == self.num_txs_sent
+            && self.is_balance_conserved()
+    }
+
+    pub fn log_summary(&self) {
+        info!(
+            "Verified {} accounts: {} of {} sent txs included, {} nonce mismatches",
+            self.num_accounts,
+            self.num_txs_included,
+            self.num_txs_sent,
+            self.nonce_mismatches.len()
+        );
+        info!(
+            "Total balance {} -> {}, burnt {}",
+            self.total_balance_before, self.total_balance_after, self.balance_burnt
+        );
+        for hash in self.dropped_txs.iter() {
+            warn!("tx {hash} was sent but not included in any chunk");
+        }
+        if !self.unsettled_accounts.is_empty() {
+            warn!(
+                "{} accounts did not settle, e.g. {}",
+                self.unsettled_accounts.len(),
+                self.unsettled_accounts[0]
+            );
+        }
+        for mismatch in self.nonce_mismatches.iter().take(10) {
+            warn!(
+                "nonce mismatch for {} key {}: local {}, on chain {}",
+                mismatch.account_id, mismatch.public_key, mismatch.local, mismatch.on_chain
+            );
+        }
+        if !self.is_balance_conserved() {
+            warn!("total balance change does not match balance burnt");
+        }
+    }
+}
+
+/// Verifies the state of `accounts` once transactions sent by a benchmark stopped landing.
+///
+/// `before` are the states of `accounts` queried before the benchmark started at `start_height`.
+/// `sent_txs` are the hashes of all transactions sent. The nonces of `accounts` are expected to be
+/// the ones of the last sent transactions. If accounts do not settle within
+/// `--verify-timeout-secs`, they are verified anyway and listed as unsettled in the report.
+pub async fn verify_native_transfers(
+    client: &RpcClient,
+    accounts: &[Account],
+    before: &[AccountState],
+    start_height: BlockHeight,
+    sent_txs: &[CryptoHash],
+    args: &VerificationArgs,
+) -> anyhow::Result<VerificationReport> {
+    let settle_interval = Duration::from_secs(args.verify_settle_secs);
+    let timeout = Duration::from_secs(args.verify_timeout_secs);
+    // Transactions might still be in the pool and receipts, including refunds, pending. Wait until
+    // the state of accounts stops changing.
+    info!("Waiting for accounts to settle before verification");
+    let started = Instant::now();
+    let mut after = query_account_states(client, accounts).await?;
+    let unsettled_accounts = loop {
+        time::sleep(settle_interval).await;
+        let latest = query_account_states(client, accounts).await?;
+        let unsettled: Vec<AccountId> = accounts
+            .iter()
+            .zip(latest.iter().zip(after.iter()))
+            .filter(|(_, (latest, after))| latest != after)
+            .map(|(account, _)| account.id.clone())
+            .collect();
+        after = latest;
+        if unsettled.is_empty() {
+            break unsettled;
+        }
+        if started.elapsed() >= timeout {
+            warn!(
+                "{} accounts did not settle within {:.0}s",
+                unsettled.len(),
+                timeout.as_secs_f64()
+            );
+            break unsettled;
+        }
+    };
+    let end_block = get_latest_block(client).await?;
+    let end_height = end_block.header.height;
+
+    let mut nonce_mismatches = vec![];
+    for (account, state) in accounts.iter().zip(after.iter()) {
+        for ((public_key, local), on_chain) in account
+            .public_keys()
+            .zip(account.nonces())
+            .zip(state.nonces.iter())
+        {
+            if local != *on_chain {
+                nonce_mismatches.push(NonceMismatch {
+                    account_id: account.id.clone(),
+                    public_key: public_key.clone(),
+                    local,
+                    on_chain: *on_chain,
+                });
+            }
+        }
+    }
+    info!("Scanning chunks of blocks {start_height}..={end_height} for sent transactions");
+    let chunk_scan = scan_chunks(client, start_height, end_block).await?;
+    let mut dropped_txs = find_dropped_txs(sent_txs, &chunk_scan.included_txs);
+    let num_txs_sent = u64::try_from(sent_txs.len()).unwrap();
+    let num_txs_included = num_txs_sent - u64::try_from(dropped_txs.len()).unwrap();
+    dropped_txs.truncate(MAX_REPORTED_DROPPED_TXS);
+
+    Ok(VerificationReport {
+        num_accounts: accounts.len(),
+        num_txs_sent,
+        num_txs_included,
+        dropped_txs,
+        unsettled_accounts,
+        nonce_mismatches,
+        total_balance_before: before.iter().map(|state| state.balance).sum(),
+        total_balance_after: after.iter().map(|state| state.balance).sum(),
+        balance_burnt: chunk_scan.balance_burnt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::transaction::SignedTransaction;
+
+    use super::*;
+
+    fn new_report(num_txs_included: u64) -> VerificationReport {
+        VerificationReport {
+            num_accounts: 2,
+            num_txs_sent: 3,
+            num_txs_included,
+            dropped_txs: vec![],
+            unsettled_accounts: vec![],
+            nonce_mismatches: vec![],
+            total_balance_before: 1000,
+            total_balance_after: 900,
+            balance_burnt: 100,
+        }
+    }
+
+    #[test]
+    fn test_is_ok() {
+        let mut report = new_report(3);
+        assert!(report.is_ok());
+
+        report.unsettled_accounts = vec!["user_0.test.near".parse().unwrap()];
+        assert!(!report.is_ok());
+        report.unsettled_accounts = vec![];
+
+        report.balance_burnt = 99;
+        assert!(!report.is_balance_conserved());
+    }
+
+    #[test]
+    fn test_find_dropped_tx_in_the_middle() {
+        let mut sender = Account::new(
+            "user_0.test.near".parse().unwrap(),
+            SecretKey::from_random(KeyType::ED25519),
+            0,
+        );
+        let receiver_id: AccountId = "user_1.test.near".parse().unwrap();
+        let sent: Vec<CryptoHash> = (0..3)
+            .map(|_| {
+                let (signer, nonce) = sender.next_signer_and_nonce();
+                SignedTransaction::send_money(
+                    nonce,
+                    sender.id.clone(),
+                    receiver_id.clone(),
+                    &signer,
+                    1,
+                    CryptoHash::default(),
+                )
+                .get_hash()
+            })
+            .collect();
+
+        // The second transaction is dropped. The nonce of the key still increases by 3 since the
+        // third transaction lands.
+        let included = HashSet::from([sent[0], sent[2]]);
+        let dropped = find_dropped_txs(&sent, &included);
+        assert_eq!(dropped, vec![sent[1]]);
+        assert!(!new_report(3 - dropped.len() as u64).is_ok());
+    }
+}
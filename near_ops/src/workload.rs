@@ -0,0 +1,463 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use clap::{Args, ValueEnum};
+use log::info;
+use near_primitives::{
+    action::{Action, FunctionCallAction, TransferAction},
+    hash::CryptoHash,
+    transaction::SignedTransaction,
+    types::{AccountId, Balance, Gas},
+};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    account::{
+        new_add_full_access_key_action, new_create_subaccount_actions, parse_key_type_selection,
+        secret_key_from_rng, Account, KeyTypeSelection,
+    },
+    contract::ArgsTemplate,
+};
+
+/// Kinds of transactions a [`Workload`] is composed of.
+#[derive(ValueEnum, Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum TxKind {
+    NativeTransfer,
+    /// `ft_transfer` of a NEP-141 token between accounts.
+    FtTransfer,
+    FunctionCall,
+    /// Creates a sub account of the sender, see [`Workload::new`] for its id.
+    CreateAccount,
+    /// Adds a full access key to the sender.
+    AddKey,
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// Weights of transaction kinds, parsed from e.g. `native-transfer=80,ft-transfer=20`.
+#[derive(Clone, Debug)]
+pub struct WorkloadMix {
+    kinds: Vec<TxKind>,
+    weights: Vec<u32>,
+    distribution: WeightedIndex<u32>,
+}
+
+impl WorkloadMix {
+    pub fn new(weights: Vec<(TxKind, u32)>) -> anyhow::Result<Self> {
+        let (kinds, weights): (Vec<_>, Vec<_>) = weights.into_iter().unzip();
+        let distribution = WeightedIndex::new(&weights)
+            .map_err(|err| anyhow::anyhow!("invalid workload weights: {err}"))?;
+        Ok(Self {
+            kinds,
+            weights,
+            distribution,
+        })
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> TxKind {
+        self.kinds[self.distribution.sample(rng)]
+    }
+
+    /// Whether transactions of `kind` are sampled.
+    pub fn contains(&self, kind: TxKind) -> bool {
+        self.kinds
+            .iter()
+            .zip(self.weights.iter())
+            .any(|(k, weight)| *k == kind && *weight > 0)
+    }
+
+    pub fn weights(&self) -> BTreeMap<TxKind, u32> {
+        self.kinds
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+            .collect()
+    }
+}
+
+impl FromStr for WorkloadMix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = vec![];
+        for entry in s.split(',') {
+            let (kind, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("expected <kind>=<weight>, got {entry}"))?;
+            let kind = TxKind::from_str(kind.trim(), true).map_err(|err| anyhow::anyhow!(err))?;
+            weights.push((kind, weight.trim().parse()?));
+        }
+        Self::new(weights)
+    }
+}
+
+/// Parses a [`WorkloadMix`], see its [`FromStr`] implementation.
+pub fn parse_workload_mix(s: &str) -> anyhow::Result<WorkloadMix> {
+    s.parse()
+}
+
+#[derive(Args, Debug)]
+pub struct WorkloadArgs {
+    /// Comma separated weights of transaction kinds, e.g.
+    /// `native-transfer=70,ft-transfer=20,function-call=5,create-account=3,add-key=2`. For each
+    /// transaction a kind is sampled according to the weights.
+    #[arg(long, value_parser = parse_workload_mix, default_value = "native-transfer=1")]
+    pub mix: WorkloadMix,
+    /// Amount of native transfers.
+    #[arg(long, default_value_t = 1)]
+    pub amount: u128,
+    /// NEP-141 contract used for FT transfers. Senders and receivers must be registered with it.
+    #[arg(long)]
+    pub ft_contract_id: Option<AccountId>,
+    /// Amount of FT transfers.
+    #[arg(long, default_value_t = 1)]
+    pub ft_amount: u128,
+    /// Contract called by function call transactions.
+    #[arg(long)]
+    pub function_call_receiver_id: Option<AccountId>,
+    #[arg(long)]
+    pub function_call_method_name: Option<String>,
+    /// Template of function call arguments, see `benchmark-function-calls --args`.
+    #[arg(long, default_value = "{}")]
+    pub function_call_args: String,
+    #[arg(long, default_value_t = 30_000_000_000_000)]
+    pub function_call_gas: Gas,
+    #[arg(long, default_value_t = 0)]
+    pub function_call_deposit: Balance,
+    /// Deposit of accounts created by `create-account` transactions.
+    #[arg(long, default_value_t = 1_000_000_000_000_000_000_000)]
+    pub create_account_deposit: Balance,
+    /// Type of keys generated by `create-account` and `add-key` transactions, see
+    /// `create-sub-accounts --key-type`.
+    #[arg(long, value_parser = parse_key_type_selection, default_value = "ed25519")]
+    pub key_type: KeyTypeSelection,
+}
+
+/// Gas attached to `ft_transfer` calls.
+const FT_TRANSFER_GAS: Gas = 10_000_000_000_000;
+
+/// A transaction generated by [`Workload::new_transaction`].
+pub struct WorkloadTx {
+    pub kind: TxKind,
+    pub transaction: SignedTransaction,
+    /// With [`TxKind::CreateAccount`], the account that is created.
+    pub new_account: Option<Account>,
+}
+
+/// A benchmark workload which is a weighted mix of transaction kinds.
+pub struct Workload {
+    mix: WorkloadMix,
+    amount: Balance,
+    ft_contract_id: Option<AccountId>,
+    ft_amount: Balance,
+    function_call_receiver_id: Option<AccountId>,
+    function_call_method_name: Option<String>,
+    function_call_args: ArgsTemplate,
+    function_call_gas: Gas,
+    function_call_deposit: Balance,
+    create_account_deposit: Balance,
+    key_type: KeyTypeSelection,
+    run_id: u64,
+}
+
+impl Workload {
+    /// Accounts created by the `i`-th transaction are named `w{run_id}_{i}.{sender}`. Passing a
+    /// distinct `run_id` to each run avoids creating accounts that already exist.
+    pub fn new(args: &WorkloadArgs, run_id: u64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !args.mix.contains(TxKind::FtTransfer) || args.ft_contract_id.is_some(),
+            "ft-transfer requires --ft-contract-id"
+        );
+        anyhow::ensure!(
+            !args.mix.contains(TxKind::FunctionCall)
+                || (args.function_call_receiver_id.is_some()
+                    && args.function_call_method_name.is_some()),
+            "function-call requires --function-call-receiver-id and --function-call-method-name"
+        );
+        Ok(Self {
+            mix: args.mix.clone(),
+            amount: args.amount,
+            ft_contract_id: args.ft_contract_id.clone(),
+            ft_amount: args.ft_amount,
+            function_call_receiver_id: args.function_call_receiver_id.clone(),
+            function_call_method_name: args.function_call_method_name.clone(),
+            function_call_args: ArgsTemplate::new(args.function_call_args.clone())?,
+            function_call_gas: args.function_call_gas,
+            function_call_deposit: args.function_call_deposit,
+            create_account_deposit: args.create_account_deposit,
+            key_type: args.key_type,
+            run_id,
+        })
+    }
+
+    pub fn mix(&self) -> &WorkloadMix {
+        &self.mix
+    }
+
+    /// Samples a transaction kind and creates a transaction of that kind signed by `sender`.
+    /// `receiver_id` is the counterparty of transfers.
+    ///
+    /// Keys added by [`TxKind::AddKey`] are not stored, so `sender` keeps signing with its
+    /// existing keys.
+    pub fn new_transaction(
+        &self,
+        sender: &mut Account,
+        receiver_id: &AccountId,
+        i: u64,
+        block_hash: CryptoHash,
+        rng: &mut impl Rng,
+    ) -> anyhow::Result<WorkloadTx> {
+        let kind = self.mix.sample(rng);
+        let (signer, nonce) = sender.next_signer_and_nonce();
+        let mut new_account = None;
+        let (tx_receiver_id, actions) = match kind {
+            TxKind::NativeTransfer => (
+                receiver_id.clone(),
+                vec![Action::Transfer(TransferAction {
+                    deposit: self.amount,
+                })],
+            ),
+            TxKind::FtTransfer => {
+                let args = serde_json::json!({
+                    "receiver_id": receiver_id,
+                    "amount": self.ft_amount.to_string(),
+                });
+                (
+                    self.ft_contract_id.clone().unwrap(),
+                    vec![function_call_action(
+                        "ft_transfer",
+                        args.to_string(),
+                        FT_TRANSFER_GAS,
+                        // NEP-141 requires attaching exactly one yoctoNEAR.
+                        1,
+                    )],
+                )
+            }
+            TxKind::FunctionCall => {
+                let contract_id = self.function_call_receiver_id.clone().unwrap();
+                let args = self
+                    .function_call_args
+                    .render(&sender.id, &contract_id, i, rng);
+                (
+                    contract_id,
+                    vec![function_call_action(
+                        self.function_call_method_name.as_ref().unwrap(),
+                        args,
+                        self.function_call_gas,
+                        self.function_call_deposit,
+                    )],
+                )
+            }
+            TxKind::CreateAccount => {
+                let account_id: AccountId =
+                    format!("w{}_{i}.{}", self.run_id, sender.id).parse()?;
+                let secret_key = secret_key_from_rng(self.key_type.key_type(i), rng);
+                let actions = new_create_subaccount_actions(
+                    secret_key.public_key(),
+                    self.create_account_deposit,
+                );
+                new_account = Some(Account::new(account_id.clone(), secret_key, 0));
+                (account_id, actions)
+            }
+            TxKind::AddKey => {
+                let secret_key = secret_key_from_rng(self.key_type.key_type(i), rng);
+                (
+                    sender.id.clone(),
+                    vec![new_add_full_access_key_action(secret_key.public_key())],
+                )
+            }
+        };
+        let transaction = SignedTransaction::from_actions(
+            nonce,
+            sender.id.clone(),
+            tx_receiver_id,
+            &signer,
+            actions,
+            block_hash,
+            0,
+        );
+        Ok(WorkloadTx {
+            kind,
+            transaction,
+            new_account,
+        })
+    }
+}
+
+fn function_call_action(method_name: &str, args: String, gas: Gas, deposit: Balance) -> Action {
+    Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: method_name.to_string(),
+        args: args.into_bytes(),
+        gas,
+        deposit,
+    }))
+}
+
+/// Composition of the transactions sent by a mixed workload benchmark.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkloadReport {
+    pub weights: BTreeMap<TxKind, u32>,
+    pub num_sent: BTreeMap<TxKind, u64>,
+}
+
+impl WorkloadReport {
+    pub fn new(mix: &WorkloadMix) -> Self {
+        Self {
+            weights: mix.weights(),
+            num_sent: BTreeMap::new(),
+        }
+    }
+
+    pub fn record_sent(&mut self, kind: TxKind) {
+        *self.num_sent.entry(kind).or_default() += 1;
+    }
+
+    pub fn log_summary(&self) {
+        for (kind, num_sent) in self.num_sent.iter() {
+            info!("Sent {num_sent} {kind} txs");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, SecretKey};
+
+    use super::*;
+    use crate::rng::seeded_rng;
+
+    fn new_workload(mix: &str) -> Workload {
+        Workload::new(
+            &WorkloadArgs {
+                mix: mix.parse().unwrap(),
+                amount: 1,
+                ft_contract_id: Some("ft.test.near".parse().unwrap()),
+                ft_amount: 5,
+                function_call_receiver_id: Some("contract.test.near".parse().unwrap()),
+                function_call_method_name: Some("set".to_string()),
+                function_call_args: r#"{"value": "{i}"}"#.to_string(),
+                function_call_gas: 1,
+                function_call_deposit: 0,
+                create_account_deposit: 10,
+                key_type: KeyTypeSelection::Secp256k1,
+            },
+            5,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_workload_mix() {
+        let mix: WorkloadMix = "native-transfer=3, FT-Transfer=1,add-key=0"
+            .parse()
+            .unwrap();
+        assert!(mix.contains(TxKind::FtTransfer));
+        assert!(!mix.contains(TxKind::AddKey));
+        assert!(!mix.contains(TxKind::CreateAccount));
+        assert_eq!(mix.weights()[&TxKind::NativeTransfer], 3);
+
+        assert!("native-transfer".parse::<WorkloadMix>().is_err());
+        assert!("bridge=1".parse::<WorkloadMix>().is_err());
+        assert!("add-key=0".parse::<WorkloadMix>().is_err());
+    }
+
+    #[test]
+    fn test_new_transaction_per_kind() {
+        let mut rng = rand::thread_rng();
+        let mut sender = Account::new(
+            "user_0.test.near".parse().unwrap(),
+            SecretKey::from_random(KeyType::ED25519),
+            0,
+        );
+        let receiver_id: AccountId = "user_1.test.near".parse().unwrap();
+
+        let tx = new_workload("ft-transfer=1")
+            .new_transaction(
+                &mut sender,
+                &receiver_id,
+                0,
+                CryptoHash::default(),
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(tx.kind, TxKind::FtTransfer);
+        assert_eq!(
+            tx.transaction.transaction.receiver_id().as_str(),
+            "ft.test.near"
+        );
+        assert_eq!(tx.transaction.transaction.nonce(), 1);
+
+        let tx = new_workload("create-account=1")
+            .new_transaction(
+                &mut sender,
+                &receiver_id,
+                7,
+                CryptoHash::default(),
+                &mut rng,
+            )
+            .unwrap();
+        let new_account = tx.new_account.unwrap();
+        assert_eq!(new_account.id.as_str(), "w5_7.user_0.test.near");
+        assert_eq!(tx.transaction.transaction.receiver_id(), &new_account.id);
+        assert_eq!(tx.transaction.transaction.actions().len(), 3);
+        assert!(matches!(
+            new_account.public_key.key_type(),
+            KeyType::SECP256K1
+        ));
+
+        // Keys are derived from the rng, so runs with the same seed create the same accounts.
+        let new_account_key = |seed| {
+            let tx = new_workload("create-account=1")
+                .new_transaction(
+                    &mut sender.clone(),
+                    &receiver_id,
+                    7,
+                    CryptoHash::default(),
+                    &mut seeded_rng(Some(seed)).0,
+                )
+                .unwrap();
+            tx.new_account.unwrap().public_key
+        };
+        assert_eq!(new_account_key(1), new_account_key(1));
+        assert_ne!(new_account_key(1), new_account_key(2));
+
+        let tx = new_workload("add-key=1")
+            .new_transaction(
+                &mut sender,
+                &receiver_id,
+                0,
+                CryptoHash::default(),
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(tx.transaction.transaction.receiver_id(), &sender.id);
+        assert_eq!(sender.nonce, 3);
+    }
+
+    #[test]
+    fn test_workload_requires_config_of_sampled_kinds() {
+        let args = WorkloadArgs {
+            mix: "ft-transfer=1".parse().unwrap(),
+            amount: 1,
+            ft_contract_id: None,
+            ft_amount: 1,
+            function_call_receiver_id: None,
+            function_call_method_name: None,
+            function_call_args: "{}".to_string(),
+            function_call_gas: 1,
+            function_call_deposit: 0,
+            create_account_deposit: 1,
+            key_type: KeyTypeSelection::Ed25519,
+        };
+        assert!(Workload::new(&args, 0).is_err());
+    }
+}